@@ -1,10 +1,21 @@
+use std::path::Path;
+
 fn main() {
+    // Generated from grammar.json by tree-sitter-dart/generate.sh
+    let parser = Path::new("tree-sitter-dart/parser.c");
+    if !parser.exists() {
+        panic!(
+            "{} is missing, run tree-sitter-dart/generate.sh to generate it",
+            parser.display()
+        );
+    }
+
     let mut build = cc::Build::new();
-    build.file("tree-sitter-dart/parser.c");
+    build.file(parser);
     // Only include scanner.c if it exists
-    if std::path::Path::new("tree-sitter-dart/scanner.c").exists() {
+    if Path::new("tree-sitter-dart/scanner.c").exists() {
         build.file("tree-sitter-dart/scanner.c");
     }
     build.flag("-w"); // Suppress all warnings
     build.compile("tree-sitter-dart");
-} 
\ No newline at end of file
+}
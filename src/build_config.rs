@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::Path;
+
+use yaml_rust2::{ScanError, Yaml, YamlLoader};
+
+use crate::json_serialization::{FieldRename, FreezedVersion, GeneratorOptions};
+
+/// The options `build.yaml` passes to the freezed and json_serializable builders, applied on
+/// top of the generator defaults the same way build_runner would
+#[derive(Debug, Default, Clone)]
+pub struct BuildConfig {
+    pub options: GeneratorOptions,
+    /// Options that were ignored, and why
+    pub warnings: Vec<String>,
+}
+
+/// A `major.minor.patch` package version, pre-release and build suffixes ignored
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// `2.5.7`, `3.0.0-dev.1` or `3`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().split(['-', '+']).next()?;
+        let mut parts = text.split('.').map(str::parse::<u64>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+/// json_serializable options that change nothing here yet
+const UNSUPPORTED_JSON_OPTIONS: &[&str] = &[
+    "constructor",
+    "create_json_keys",
+    "generic_argument_factories",
+    "ignore_unannotated",
+];
+
+/// freezed options that change nothing here yet
+const UNSUPPORTED_FREEZED_OPTIONS: &[&str] = &[
+    "copy_with",
+    "equal",
+    "to_string",
+    "from_json",
+    "to_json",
+    "map",
+    "when",
+    "generic_argument_factories",
+];
+
+impl BuildConfig {
+    /// Reads `build.yaml` in `package_root`, the defaults when there's none, and the
+    /// generator versions from the closest `pubspec.lock`
+    pub fn load(package_root: &Path, defaults: &GeneratorOptions) -> Self {
+        let path = package_root.join("build.yaml");
+        let mut config = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let mut config = Self::parse(&content, defaults);
+                for warning in &mut config.warnings {
+                    *warning = format!("{}: {warning}", path.display());
+                }
+                config
+            }
+            Err(_) => Self {
+                options: defaults.clone(),
+                warnings: Vec::new(),
+            },
+        };
+
+        // Workspace packages share the lock file of the workspace root
+        let lock = package_root
+            .ancestors()
+            .find_map(|e| fs::read_to_string(e.join("pubspec.lock")).ok());
+        if let Some(lock) = lock {
+            config.apply_lock(&lock);
+        }
+        config
+    }
+
+    /// Takes the resolved freezed and json_serializable versions of a `pubspec.lock`
+    pub fn apply_lock(&mut self, lock: &str) {
+        let yaml = match parse_yaml(lock) {
+            Ok(yaml) => yaml,
+            Err(error) => {
+                self.warnings.push(format!(
+                    "couldn't parse pubspec.lock ({error}), using the latest versions"
+                ));
+                return;
+            }
+        };
+        let version =
+            |package: &str| Version::parse(yaml["packages"][package]["version"].as_str()?);
+
+        if let Some(version) = version("freezed") {
+            self.options.freezed_version = FreezedVersion::from_version(&version);
+        }
+        if let Some(version) = version("json_serializable") {
+            self.options.json_serializable_version = Some(version);
+        }
+    }
+
+    pub fn parse(content: &str, defaults: &GeneratorOptions) -> Self {
+        let mut config = Self {
+            options: defaults.clone(),
+            warnings: Vec::new(),
+        };
+
+        let yaml = match parse_yaml(content) {
+            Ok(yaml) => yaml,
+            Err(error) => {
+                config.warnings.push(format!(
+                    "couldn't parse it ({error}), using the default options"
+                ));
+                return config;
+            }
+        };
+
+        // `global_options` apply to every target, so they go first
+        let builder_sets = std::iter::once(&yaml["global_options"])
+            .chain(entries(&yaml["targets"]).map(|e| &e.1["builders"]));
+
+        for builders in builder_sets {
+            for (key, builder) in entries(builders) {
+                let options = &builder["options"];
+                if options.is_badvalue() || options.is_null() {
+                    continue;
+                }
+                if options.as_hash().is_none() {
+                    config.warnings.push(format!(
+                        "`options` of builder `{key}` should be a mapping, ignoring them"
+                    ));
+                    continue;
+                }
+                match builder_name(key) {
+                    "json_serializable" => config.apply_json_options(options),
+                    "freezed" => config.apply_freezed_options(options),
+                    _ => {}
+                }
+            }
+        }
+
+        config
+    }
+
+    fn apply_json_options(&mut self, options: &Yaml) {
+        let json = &mut self.options.json;
+        for (key, value) in entries(options) {
+            let flag = match key {
+                "any_map" => &mut json.any_map,
+                "checked" => &mut json.checked,
+                "create_factory" => &mut json.create_factory,
+                "create_field_map" => &mut json.create_field_map,
+                "create_per_field_to_json" => &mut json.create_per_field_to_json,
+                "create_to_json" => &mut json.create_to_json,
+                "disallow_unrecognized_keys" => &mut json.disallow_unrecognized_keys,
+                "explicit_to_json" => &mut json.explicit_to_json,
+                "include_if_null" => &mut json.include_if_null,
+                "field_rename" => {
+                    match value.as_str().and_then(FieldRename::parse) {
+                        Some(field_rename) => json.field_rename = field_rename,
+                        None => self.warnings.push(format!(
+                            "json_serializable option `field_rename` has an invalid value {value:?}"
+                        )),
+                    }
+                    continue;
+                }
+                name if UNSUPPORTED_JSON_OPTIONS.contains(&name) => {
+                    self.warnings.push(format!(
+                        "json_serializable option `{name}` isn't supported, ignoring it"
+                    ));
+                    continue;
+                }
+                name => {
+                    self.warnings.push(format!(
+                        "unknown json_serializable option `{name}`, ignoring it"
+                    ));
+                    continue;
+                }
+            };
+
+            match value.as_bool() {
+                Some(value) => *flag = value,
+                None => self.warnings.push(format!(
+                    "json_serializable option `{key}` should be true or false, not {value:?}"
+                )),
+            }
+        }
+    }
+
+    fn apply_freezed_options(&mut self, options: &Yaml) {
+        let freezed = &mut self.options.freezed;
+        for (key, value) in entries(options) {
+            match key {
+                // Generated code is never formatted, whatever is asked
+                "format" => {}
+                "union_key" => match value.as_str() {
+                    Some(union_key) if !union_key.is_empty() => {
+                        freezed.union_key = union_key.to_owned();
+                    }
+                    _ => self.warnings.push(format!(
+                        "freezed option `union_key` has an invalid value {value:?}"
+                    )),
+                },
+                "union_value_case" => match value.as_str().and_then(FieldRename::parse) {
+                    Some(union_value_case) => freezed.union_value_case = union_value_case,
+                    None => self.warnings.push(format!(
+                        "freezed option `union_value_case` has an invalid value {value:?}"
+                    )),
+                },
+                name if UNSUPPORTED_FREEZED_OPTIONS.contains(&name) => {
+                    self.warnings.push(format!(
+                        "freezed option `{name}` isn't supported, ignoring it"
+                    ));
+                }
+                name => {
+                    self.warnings
+                        .push(format!("unknown freezed option `{name}`, ignoring it"));
+                }
+            }
+        }
+    }
+}
+
+/// `json_serializable` from `json_serializable`, `json_serializable:json_serializable` or
+/// `json_serializable|json_serializable`
+fn builder_name(key: &str) -> &str {
+    key.split([':', '|']).next().unwrap_or(key)
+}
+
+/// The first document of `content`, `Null` when there's none
+fn parse_yaml(content: &str) -> Result<Yaml, ScanError> {
+    let documents = YamlLoader::load_from_str(content)?;
+    Ok(documents.into_iter().next().unwrap_or(Yaml::Null))
+}
+
+/// The entries of a mapping with string keys, nothing for any other value
+fn entries(yaml: &Yaml) -> impl Iterator<Item = (&str, &Yaml)> {
+    yaml.as_hash()
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| Some((key.as_str()?, value)))
+}
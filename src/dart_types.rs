@@ -1,5 +1,32 @@
+use std::fmt;
+
+/// 1-based line and column, the column counted in bytes like tree-sitter does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Location of a parsed element in its source file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.start.line, self.start.column)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct RedirectedConstructor {
+    pub span: Span,
+    pub documentation: Option<String>,
+    pub annotations: Vec<Annotation>,
     pub is_const: bool,
     pub class_name: String,
     pub constructor_name: Option<String>,
@@ -40,6 +67,8 @@ impl ParameterList {
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct PositionalParameter {
+    pub span: Span,
+    pub documentation: Option<String>,
     pub name: String,
     pub dart_type: DartType,
     pub annotations: Vec<Annotation>,
@@ -47,6 +76,8 @@ pub struct PositionalParameter {
 
 #[derive(Debug, Default)]
 pub struct NamedParameter {
+    pub span: Span,
+    pub documentation: Option<String>,
     pub annotations: Vec<Annotation>,
     pub is_required: bool,
     pub dart_type: DartType,
@@ -57,6 +88,8 @@ pub struct NamedParameter {
 impl NamedParameter {
     pub fn to_positional(&self) -> PositionalParameter {
         PositionalParameter {
+            span: self.span,
+            documentation: self.documentation.clone(),
             name: self.name.clone(),
             dart_type: self.dart_type.clone(),
             annotations: self.annotations.clone(),
@@ -64,35 +97,73 @@ impl NamedParameter {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone)]
 pub struct Annotation {
+    pub span: Span,
+    /// The annotation as written, e.g. `@JsonKey(name: 'id')`
+    pub source: String,
     pub name: String,
+    /// Generic arguments, e.g. `Comparable<Foo>` in `@Implements<Comparable<Foo>>()`
+    pub type_arguments: Vec<DartType>,
     pub arguments: Vec<String>,
 }
 
+/// The same annotation wherever it's written, so not by span or formatting
+impl PartialEq for Annotation {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.type_arguments == other.type_arguments
+            && self.arguments == other.arguments
+    }
+}
+
+impl Eq for Annotation {}
+
 impl Annotation {
-    pub fn get_default_value(&self) -> String {
-        assert_eq!(self.name, "Default");
-        let argument = self.arguments.first().unwrap();
+    /// Both the `@freezed` shorthand and the configurable `@Freezed(...)` form
+    pub fn is_freezed(&self) -> bool {
+        self.name == "freezed" || self.name == "Freezed"
+    }
+
+    /// Annotations only meaningful to freezed itself, which aren't copied to generated fields
+    pub fn is_freezed_internal(&self) -> bool {
+        ["Default", "Implements", "With"].contains(&self.name.as_str())
+    }
+
+    /// Value of a `name: value` argument, e.g. `checked` in `@JsonSerializable(checked: true)`
+    pub fn get_named_argument(&self, name: &str) -> Option<&str> {
+        self.arguments.iter().find_map(|argument| {
+            let (label, value) = argument.split_once(':')?;
+            (label.trim() == name).then(|| value.trim())
+        })
+    }
+
+    /// The value of a `@Default(...)`, `None` for other annotations. The parser reports a
+    /// `@Default` without exactly one value, so it's never generated.
+    pub fn get_default_value(&self) -> Option<String> {
+        if self.name != "Default" {
+            return None;
+        }
+        let argument = self.arguments.first()?;
         // TODO, remoev this shit match
         match argument.as_str() {
             "true" | "false" | "null" => {
-                return argument.to_string();
+                return Some(argument.to_string());
             }
             _ if argument.chars().all(|c| c.is_ascii_digit() || c == '.') => {
-                return argument.to_string();
+                return Some(argument.to_string());
             }
             _ if argument.starts_with("'") && argument.ends_with("'") => {
-                return argument.to_string();
+                return Some(argument.to_string());
             }
             _ => {}
         }
 
         // Here's fucking GG
         if argument.ends_with(')') || argument.ends_with(']') || argument.ends_with('}') {
-            format!("const {argument}")
+            Some(format!("const {argument}"))
         } else {
-            argument.to_string()
+            Some(argument.to_string())
         }
     }
 }
@@ -127,12 +198,105 @@ impl DartType {
         raw
     }
 
+    /// Reads a type written as source text, for the places the grammar can't parse.
+    /// Function and record types are kept whole in `name`, like the parser does.
+    pub fn parse_raw(text: &str) -> DartType {
+        let text = text.trim();
+        let (text, nullable) = match text.strip_suffix('?') {
+            Some(text) if text.ends_with(['>', ')']) || !text.contains(['<', '(']) => {
+                (text.trim_end(), true)
+            }
+            _ => (text, false),
+        };
+
+        let generic = text
+            .split_once('<')
+            .filter(|(name, rest)| !name.contains(['(', ' ']) && rest.ends_with('>'));
+        let Some((name, arguments)) = generic else {
+            return DartType {
+                name: text.to_owned(),
+                nullable,
+                type_arguments: Vec::new(),
+            };
+        };
+
+        let arguments = &arguments[..arguments.len() - 1];
+        DartType {
+            name: name.trim().to_owned(),
+            nullable,
+            type_arguments: split_top_level(arguments)
+                .into_iter()
+                .map(DartType::parse_raw)
+                .collect(),
+        }
+    }
+
+    /// Closest common supertype known without resolving user classes: `int` and
+    /// `double` meet at `num`, generics are merged argument by argument, anything
+    /// else falls back to `Object`. Nullable if either side is.
+    ///
+    /// `GenerationContext::least_upper_bound` walks the project's class hierarchy first.
+    pub fn least_upper_bound(&self, other: &DartType) -> DartType {
+        let nullable = self.nullable || other.nullable;
+        let is_dynamic = |e: &DartType| e.name.is_empty() || e.name == "dynamic";
+
+        if is_dynamic(self) || is_dynamic(other) {
+            return DartType::default();
+        }
+
+        if self.name == other.name && self.type_arguments.len() == other.type_arguments.len() {
+            return DartType {
+                name: self.name.clone(),
+                nullable,
+                type_arguments: self
+                    .type_arguments
+                    .iter()
+                    .zip(&other.type_arguments)
+                    .map(|(a, b)| a.least_upper_bound(b))
+                    .collect(),
+            };
+        }
+
+        let is_number = |e: &DartType| ["int", "double", "num"].contains(&e.name.as_str());
+        let name = if is_number(self) && is_number(other) {
+            "num"
+        } else {
+            "Object"
+        };
+
+        DartType {
+            name: name.to_string(),
+            nullable,
+            type_arguments: Vec::new(),
+        }
+    }
+
     // Temporary
     pub fn is_collection(&self) -> bool {
         ["List", "Map", "Set"].contains(&self.name.as_str())
     }
 }
 
+/// Splits on the commas not nested in brackets, `Map<String, int>, (int, int)` in two
+pub fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' | ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
 pub fn get_generic_string(types: &[DartType]) -> String {
     if types.is_empty() {
         return "".to_string();
@@ -151,12 +315,105 @@ pub fn get_generic_string(types: &[DartType]) -> String {
 
 #[derive(Debug)]
 pub struct ClassDefinition {
+    pub span: Span,
     pub name: String,
-    pub gen_form: bool,
+    /// Every annotation on the class, in source order
+    pub annotations: Vec<Annotation>,
+    /// Raw `///` comment lines preceding the class
+    pub documentation: Option<String>,
+    /// Declared `abstract` or `sealed`, so it's never instantiated itself
+    pub is_abstract: bool,
     pub mixins: Vec<DartType>,
+    pub interfaces: Vec<DartType>,
+    pub superclass: Option<DartType>,
     pub json_constructor: Option<RedirectedConstructor>,
     pub unnamed_constructor: Option<RedirectedConstructor>,
     pub redirecting_constructors: Vec<RedirectedConstructor>,
+    /// Unnamed `Foo(...)` constructor of classes declaring their own fields
+    pub generative_constructor: Option<RedirectedConstructor>,
+    pub fields: Vec<PositionalParameter>,
 }
 
-impl ClassDefinition {}
+impl ClassDefinition {
+    pub fn get_annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|e| e.name == name)
+    }
+
+    pub fn has_annotation(&self, name: &str) -> bool {
+        self.get_annotation(name).is_some()
+    }
+
+    pub fn is_freezed(&self) -> bool {
+        self.annotations.iter().any(Annotation::is_freezed)
+    }
+
+    /// `@JsonSerializable` on the class or, as freezed allows it, on its constructors
+    pub fn json_serializable_annotations(&self) -> impl Iterator<Item = &Annotation> {
+        let constructor_annotations = self
+            .redirecting_constructors
+            .iter()
+            .chain(&self.generative_constructor)
+            .flat_map(|e| &e.annotations);
+        self.annotations
+            .iter()
+            .chain(constructor_annotations)
+            .filter(|e| e.name == "JsonSerializable")
+    }
+
+    /// e.g. `true` for `checked` in `@JsonSerializable(checked: true)`
+    pub fn get_json_serializable_argument(&self, name: &str) -> Option<&str> {
+        self.json_serializable_annotations()
+            .find_map(|e| e.get_named_argument(name))
+    }
+
+    /// Name of the generated function the `fromJson` factory forwards to
+    pub fn from_json_function(&self) -> Option<&str> {
+        self.json_constructor
+            .as_ref()
+            .map(|e| e.assigned_type.name.as_str())
+    }
+
+    /// The generated `_$Foo` mixin, wherever it appears in the `with` clause
+    pub fn freezed_mixin(&self) -> DartType {
+        let mixin_name = format!("_${}", self.name);
+        self.mixins
+            .iter()
+            .find(|e| e.name == mixin_name)
+            .cloned()
+            .unwrap_or(DartType {
+                name: mixin_name,
+                ..Default::default()
+            })
+    }
+
+    /// What the class extends, mixes in and implements, bar the generated `_$Foo` mixin
+    pub fn supertypes(&self) -> Vec<DartType> {
+        let mixin_name = format!("_${}", self.name);
+        self.superclass
+            .iter()
+            .chain(self.mixins.iter().filter(|e| e.name != mixin_name))
+            .chain(&self.interfaces)
+            .cloned()
+            .collect()
+    }
+}
+
+impl RedirectedConstructor {
+    /// Types from `@Implements<T>()`, which freezed adds to the generated case class
+    pub fn implemented_types(&self) -> Vec<DartType> {
+        self.annotated_types("Implements")
+    }
+
+    /// Types from `@With<T>()`, which freezed mixes into the generated case class
+    pub fn mixed_in_types(&self) -> Vec<DartType> {
+        self.annotated_types("With")
+    }
+
+    fn annotated_types(&self, annotation: &str) -> Vec<DartType> {
+        self.annotations
+            .iter()
+            .filter(|e| e.name == annotation)
+            .flat_map(|e| e.type_arguments.iter().cloned())
+            .collect()
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt::Write;
 
 use crate::dart_types::{DartType, ParameterList, get_generic_string};
 
+use super::{FreezedVersion, GenerationContext, generate_documentation};
+
 pub fn generate_mixin_copywith_function(
     output: &mut String,
     class_name: &str,
@@ -18,6 +20,7 @@ pub fn generate_mixin_copywith_function(
 
 pub fn generate_abstract_copywith_mixin(
     output: &mut String,
+    context: &GenerationContext,
     class_name: &str,
     class_generics: &[DartType],
     implements: Option<&str>,
@@ -51,9 +54,14 @@ pub fn generate_abstract_copywith_mixin(
         return;
     }
 
+    // `mixin class` needs Dart 3, which freezed 2 doesn't require
+    let declaration = match context.options.freezed_version {
+        FreezedVersion::V2 => "abstract class",
+        FreezedVersion::V3 => "abstract mixin class",
+    };
     let _ = writeln!(
         output,
-        "abstract mixin class ${class_name}CopyWith{copywith_generics} {}{{",
+        "{declaration} ${class_name}CopyWith{copywith_generics} {}{{",
         if let Some(class) = implements {
             format!("implements ${class}CopyWith{copywith_generics} ")
         } else {
@@ -69,6 +77,7 @@ pub fn generate_abstract_copywith_mixin(
     );
 
     for field in fields.get_all_params() {
+        generate_documentation(output, &field.documentation, "    ");
         let _ = writeln!(output, "    {} {},", field.dart_type.as_raw(), field.name);
     }
 
@@ -78,6 +87,7 @@ pub fn generate_abstract_copywith_mixin(
 
 pub fn generate_copywith_impl_mixin(
     output: &mut String,
+    context: &GenerationContext,
     class_name: &str,
     class_generics: &[DartType],
     fields: &ParameterList,
@@ -101,13 +111,14 @@ pub fn generate_copywith_impl_mixin(
   final $Res Function({class_name}{just_generics}) _then;
 "
     );
-    generate_impl_function(output, class_name, fields, has_constructor);
+    generate_impl_function(output, context, class_name, fields, has_constructor);
 
     let _ = writeln!(output, "}}");
 }
 
 pub fn generate_impl_function(
     output: &mut String,
+    context: &GenerationContext,
     class_name: &str,
     fields: &ParameterList,
     has_constructor: bool,
@@ -119,7 +130,8 @@ pub fn generate_impl_function(
   $Res call({{"
     );
     for field in fields.get_all_params() {
-        if field.dart_type.nullable || field.dart_type.name.is_empty() {
+        let dart_type = context.expand_type(&field.dart_type);
+        if dart_type.nullable || dart_type.name.is_empty() {
             let _ = writeln!(output, "    Object? {} = freezed,", field.name);
         } else {
             let _ = writeln!(output, "    Object? {} = null,", field.name);
@@ -134,14 +146,14 @@ pub fn generate_impl_function(
         );
 
         for pos_field in &fields.positional_parameters {
-            generate_copywith_element(output, &pos_field.name, &pos_field.dart_type);
+            generate_copywith_element(output, context, &pos_field.name, &pos_field.dart_type);
             let _ = write!(output, ",");
         }
 
         if !fields.named_parameters.is_empty() {
             for field in &fields.named_parameters {
                 let _ = write!(output, "{}: ", field.name);
-                generate_copywith_element(output, &field.name, &field.dart_type);
+                generate_copywith_element(output, context, &field.name, &field.dart_type);
                 let _ = write!(output, ",");
             }
         }
@@ -156,7 +168,7 @@ pub fn generate_impl_function(
         if !all_params.is_empty() {
             for field in all_params {
                 let _ = write!(output, "{}: ", field.name);
-                generate_copywith_element(output, &field.name, &field.dart_type);
+                generate_copywith_element(output, context, &field.name, &field.dart_type);
                 let _ = write!(output, ",");
             }
         }
@@ -166,8 +178,15 @@ pub fn generate_impl_function(
     let _ = writeln!(output, "  }}");
 }
 
-pub fn generate_copywith_element(output: &mut String, name: &str, dart_type: &DartType) {
-    if dart_type.nullable || dart_type.name.is_empty() {
+/// Strategy picked on the expanded type, the cast keeps the alias
+pub fn generate_copywith_element(
+    output: &mut String,
+    context: &GenerationContext,
+    name: &str,
+    dart_type: &DartType,
+) {
+    let expanded_type = context.expand_type(dart_type);
+    if expanded_type.nullable || expanded_type.name.is_empty() {
         let _ = writeln!(
             output,
             "freezed == {name} ? _self.{name} : {name} as {}",
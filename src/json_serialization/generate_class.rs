@@ -1,37 +1,385 @@
 use crate::{
-    dart_types::{ClassDefinition, DartType, ParameterList, PositionalParameter},
+    build_config::Version,
+    dart_types::{
+        ClassDefinition, DartType, ParameterList, PositionalParameter, RedirectedConstructor,
+    },
     json_serialization::generate_introspection_class,
+    package_config::PackageConfig,
+    project_index::{ProjectIndex, Representation, Scope, Symbol, SymbolKind},
 };
 
 use super::{
-    JsonMethod, from_json_function_generator, generate_abstract_copywith_mixin,
-    generate_copywith_impl_mixin, generate_mixin, generate_solo_class, to_json_function_generator,
+    FieldRename, JsonMethod, JsonOptions, enum_maps_generator, field_map_generator,
+    from_json_function_generator, generate_abstract_copywith_mixin, generate_copywith_impl_mixin,
+    generate_documentation, generate_mixin, generate_solo_class, per_field_to_json_generator,
+    to_json_function_generator, union_from_json_generator,
 };
 
-pub fn intersection_positional_parameters(
-    vec1: &mut Vec<PositionalParameter>,
-    vec2: &[PositionalParameter],
-) {
-    let mut i = 0;
+/// What the generator knows beyond the class it's generating
+#[derive(Debug, Clone, Copy)]
+pub struct GenerationContext<'a> {
+    pub index: &'a ProjectIndex,
+    /// Types visible from the file being generated
+    pub scope: &'a Scope,
+    /// Resolves the imports of other files, for the scope of the typedefs they declare
+    pub config: &'a PackageConfig,
+    pub options: &'a GeneratorOptions,
+}
+
+/// Project-wide defaults, overridden by each class's annotations
+#[derive(Debug, Default, Clone)]
+pub struct GeneratorOptions {
+    pub json: JsonOptions,
+    pub freezed: FreezedOptions,
+    /// The freezed the code is generated for, its output changed between majors
+    pub freezed_version: FreezedVersion,
+    /// The json_serializable in use, `None` for the latest
+    pub json_serializable_version: Option<Version>,
+}
+
+/// freezed's own options, overridden by `@Freezed(...)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreezedOptions {
+    /// The JSON key telling the cases of a union apart
+    pub union_key: String,
+    /// How constructor names become the values of `union_key`
+    pub union_value_case: FieldRename,
+}
+
+impl Default for FreezedOptions {
+    fn default() -> Self {
+        Self {
+            union_key: "runtimeType".to_owned(),
+            union_value_case: FieldRename::None,
+        }
+    }
+}
+
+impl FreezedOptions {
+    pub fn for_class(context: &GenerationContext, class: &ClassDefinition) -> FreezedOptions {
+        let mut options = context.options.freezed.clone();
+        let Some(annotation) = class.get_annotation("Freezed") else {
+            return options;
+        };
+
+        if let Some(union_key) = annotation.get_named_argument("unionKey") {
+            options.union_key = union_key.trim_matches(['\'', '"']).to_owned();
+        }
+        if let Some(union_value_case) = annotation
+            .get_named_argument("unionValueCase")
+            .and_then(|e| FieldRename::parse(e.trim_start_matches("FreezedUnionCase.")))
+        {
+            options.union_value_case = union_value_case;
+        }
+        options
+    }
 
-    while i < vec1.len() {
-        let item = &vec1[i];
-        let iniside = vec2
+    /// What `union_key` holds for `constructor`, `@FreezedUnionValue` or its name, `default`
+    /// for the unnamed one
+    pub fn union_value(&self, constructor: &RedirectedConstructor) -> String {
+        if let Some(value) = constructor
+            .annotations
             .iter()
-            .any(|e| e.name == item.name && e.dart_type == item.dart_type);
+            .find(|e| e.name == "FreezedUnionValue")
+            .and_then(|e| e.arguments.first())
+        {
+            return value.trim_matches(['\'', '"']).to_owned();
+        }
 
-        if !iniside {
-            vec1.remove(i);
+        match &constructor.constructor_name {
+            Some(name) => self.union_value_case.apply(name),
+            None => "default".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FreezedVersion {
+    /// Classes may be concrete, so the mixin throws instead of declaring abstract members,
+    /// and unions get `when` and `map`
+    V2,
+    #[default]
+    V3,
+}
+
+impl FreezedVersion {
+    pub fn from_version(version: &Version) -> Self {
+        if version.major < 3 {
+            Self::V2
         } else {
-            i += 1;
+            Self::V3
+        }
+    }
+}
+
+impl<'a> GenerationContext<'a> {
+    pub fn new(
+        index: &'a ProjectIndex,
+        scope: &'a Scope,
+        config: &'a PackageConfig,
+        options: &'a GeneratorOptions,
+    ) -> Self {
+        Self {
+            index,
+            scope,
+            config,
+            options,
+        }
+    }
+
+    /// The declaration a type name refers to, if it's declared in the project. Names
+    /// prefixed with an import prefix, `p.Address`, are looked up in that import only.
+    pub fn resolve(&self, name: &str) -> Option<&'a Symbol> {
+        let bare_name = name.rsplit('.').next().unwrap_or(name);
+
+        // Not guessed by name when out of scope, in a monorepo that could be a type of an
+        // unrelated package. Unresolved imports are packages outside the project anyway.
+        self.index
+            .get_declared_in(bare_name, self.scope.declaring_files(name))
+    }
+
+    /// `dart_type` with typedefs replaced by what they alias, type arguments included, so
+    /// strategies are picked on the real type. Declarations keep using the alias.
+    pub fn expand_type(&self, dart_type: &DartType) -> DartType {
+        self.expand_type_inner(dart_type, 0)
+    }
+
+    fn expand_type_inner(&self, dart_type: &DartType, depth: usize) -> DartType {
+        let expanded = DartType {
+            name: dart_type.name.clone(),
+            nullable: dart_type.nullable,
+            type_arguments: dart_type
+                .type_arguments
+                .iter()
+                .map(|e| self.expand_type_inner(e, depth))
+                .collect(),
+        };
+
+        // Bounded, as `typedef A = B; typedef B = A;` is only an analyzer error
+        let Some(symbol) = self.resolve(&dart_type.name).filter(|_| depth < 16) else {
+            return expanded;
+        };
+        let Some(aliased_type) = &symbol.aliased_type else {
+            return expanded;
+        };
+
+        // Names inside a typedef are written in the scope of its own file, the arguments
+        // it's used with in this one, so they're substituted once expanded
+        let alias_scope = self.index.scope_for(&symbol.path, self.config);
+        let alias_context = GenerationContext {
+            scope: &alias_scope,
+            ..*self
+        };
+        let mut aliased = alias_context.expand_type_inner(aliased_type, depth + 1);
+        substitute_type_parameters(
+            &mut aliased,
+            &symbol.type_parameters,
+            &expanded.type_arguments,
+        );
+        aliased.nullable |= expanded.nullable;
+        aliased
+    }
+
+    /// Closest common supertype of two types, walking the `extends`, `with` and
+    /// `implements` clauses of the project's classes. Like Dart, it's the deepest shared
+    /// supertype when no other is as deep, otherwise `DartType::least_upper_bound` decides.
+    pub fn least_upper_bound(&self, a: &DartType, b: &DartType) -> DartType {
+        let is_dynamic = |e: &DartType| e.name.is_empty() || e.name == "dynamic";
+        if is_dynamic(a) || is_dynamic(b) {
+            return a.least_upper_bound(b);
+        }
+        if a.name == b.name && a.type_arguments.len() == b.type_arguments.len() {
+            return DartType {
+                name: a.name.clone(),
+                nullable: a.nullable || b.nullable,
+                type_arguments: a
+                    .type_arguments
+                    .iter()
+                    .zip(&b.type_arguments)
+                    .map(|(a, b)| self.least_upper_bound(a, b))
+                    .collect(),
+            };
+        }
+
+        let b_supertypes = self.supertypes(b, 0);
+        let shared: Vec<_> = self
+            .supertypes(a, 0)
+            .into_iter()
+            .filter_map(|(a, depth)| {
+                let (b, _) = b_supertypes.iter().find(|e| e.0.name == a.name)?;
+                Some((a, b.clone(), depth))
+            })
+            .collect();
+
+        let mut depths: Vec<_> = shared.iter().map(|e| e.2).collect();
+        depths.sort_unstable();
+        depths.dedup();
+        for depth in depths.into_iter().rev() {
+            let mut candidates = shared.iter().filter(|e| e.2 == depth);
+            if let (Some((a_supertype, b_supertype, _)), None) =
+                (candidates.next(), candidates.next())
+            {
+                let mut bound = self.least_upper_bound(a_supertype, b_supertype);
+                bound.nullable = a.nullable || b.nullable;
+                return bound;
+            }
+        }
+        a.least_upper_bound(b)
+    }
+
+    /// `dart_type` and every class it extends, mixes in or implements, directly or not,
+    /// with the type arguments it's used with. Each is paired with its depth, the longest
+    /// path from it to `Object`, types outside the project counting as direct subtypes.
+    fn supertypes(&self, dart_type: &DartType, guard: usize) -> Vec<(DartType, usize)> {
+        let mut supertypes: Vec<(DartType, usize)> = Vec::new();
+        let mut depth = 1;
+
+        // Bounded, as a cycle is only an analyzer error
+        if let Some(symbol) = self.resolve(&dart_type.name).filter(|_| guard < 16) {
+            // Written in the scope of the file declaring the class
+            let symbol_scope = self.index.scope_for(&symbol.path, self.config);
+            let symbol_context = GenerationContext {
+                scope: &symbol_scope,
+                ..*self
+            };
+            for supertype in &symbol.supertypes {
+                let mut supertype = supertype.clone();
+                substitute_type_parameters(
+                    &mut supertype,
+                    &symbol.type_parameters,
+                    &dart_type.type_arguments,
+                );
+                let inherited = symbol_context.supertypes(&supertype, guard + 1);
+                depth = depth.max(inherited[0].1 + 1);
+                for inherited in inherited {
+                    if !supertypes.iter().any(|e| e.0.name == inherited.0.name) {
+                        supertypes.push(inherited);
+                    }
+                }
+            }
+        }
+
+        let own_type = DartType {
+            nullable: false,
+            ..dart_type.clone()
+        };
+        supertypes.insert(0, (own_type, depth));
+        supertypes
+    }
+
+    pub fn extension_type(&self, dart_type: &DartType) -> Option<&'a Symbol> {
+        self.resolve(&self.expand_type(dart_type).name)
+            .filter(|e| e.kind == SymbolKind::ExtensionType)
+    }
+
+    /// What an extension type wraps, to be used when it has no JSON methods of its own
+    pub fn representation(&self, dart_type: &DartType) -> Option<&'a Representation> {
+        self.extension_type(dart_type)
+            .filter(|e| !e.has_from_json)?
+            .representation
+            .as_ref()
+    }
+
+    /// The type equality and hashing work on: typedefs expanded and extension types
+    /// replaced by what they wrap, since at runtime they're the same object
+    pub fn runtime_type(&self, dart_type: &DartType) -> DartType {
+        let expanded = self.expand_type(dart_type);
+        match self
+            .extension_type(&expanded)
+            .and_then(|e| e.representation.as_ref())
+        {
+            Some(representation) => {
+                let mut wrapped = self.expand_type(&representation.dart_type);
+                wrapped.nullable |= expanded.nullable;
+                wrapped
+            }
+            None => expanded,
         }
     }
+
+    pub fn is_enum(&self, dart_type: &DartType) -> bool {
+        self.resolve(&self.expand_type(dart_type).name)
+            .is_some_and(|e| e.kind == SymbolKind::Enum)
+    }
 }
 
-pub fn generate_class(output: &mut String, json_output: &mut String, class: &ClassDefinition) {
-    let mixin_type = class.mixins.first().unwrap().as_raw();
+/// Replaces the typedef's own type parameters with the arguments it was used with,
+/// missing arguments become `dynamic`
+fn substitute_type_parameters(
+    dart_type: &mut DartType,
+    type_parameters: &[String],
+    type_arguments: &[DartType],
+) {
+    if dart_type.type_arguments.is_empty()
+        && let Some(position) = type_parameters.iter().position(|e| *e == dart_type.name)
+    {
+        let nullable = dart_type.nullable;
+        *dart_type = type_arguments.get(position).cloned().unwrap_or_default();
+        dart_type.nullable |= nullable;
+        return;
+    }
+
+    for type_argument in &mut dart_type.type_arguments {
+        substitute_type_parameters(type_argument, type_parameters, type_arguments);
+    }
+}
+
+/// Fields declared by every union case. A field whose types differ between cases is
+/// exposed with their least upper bound, which `copyWith` can't accept, so the second
+/// list only keeps the fields with the same type everywhere.
+pub fn shared_union_fields(
+    context: &GenerationContext,
+    constructors: &[RedirectedConstructor],
+) -> (Vec<PositionalParameter>, Vec<PositionalParameter>) {
+    let Some((first, rest)) = constructors.split_first() else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let mut shared_fields = Vec::new();
+    let mut copyable_fields = Vec::new();
+
+    'fields: for field in first.parameters.get_all_params() {
+        let mut merged = field.clone();
+        let mut is_copyable = true;
+
+        for constructor in rest {
+            let Some(other) = constructor
+                .parameters
+                .get_all_params()
+                .into_iter()
+                .find(|e| e.name == field.name)
+            else {
+                continue 'fields;
+            };
+
+            is_copyable &= other.dart_type == field.dart_type;
+            merged.dart_type = context.least_upper_bound(&merged.dart_type, &other.dart_type);
+        }
 
-    let class_generics = class.mixins.first().unwrap().type_arguments.to_owned();
+        if is_copyable {
+            copyable_fields.push(merged.clone());
+        }
+        shared_fields.push(merged);
+    }
+
+    (shared_fields, copyable_fields)
+}
+
+pub fn generate_class(
+    output: &mut String,
+    json_output: &mut String,
+    class: &ClassDefinition,
+    context: &GenerationContext,
+) {
+    if !class.is_freezed() {
+        generate_json_serializable_class(json_output, class, context);
+        return;
+    }
+
+    let freezed_mixin = class.freezed_mixin();
+    let mixin_type = freezed_mixin.as_raw();
+
+    let class_generics = freezed_mixin.type_arguments.to_owned();
     let mut copywith_generics = class_generics.clone();
     let class_name = class.name.clone();
     // That's a warcrime, check if can be done without nesting, just plain
@@ -48,22 +396,18 @@ pub fn generate_class(output: &mut String, json_output: &mut String, class: &Cla
     // let just_generics = get_generic_string(&class_generics);
     // let copywith_generics = get_generic_string(&copywith_generics);
 
-    let mut intersecting_fields = class
-        .redirecting_constructors
-        .first()
-        .unwrap()
-        .parameters
-        .get_all_params();
-
-    for constructor in &class.redirecting_constructors {
-        intersection_positional_parameters(
-            &mut intersecting_fields,
-            &constructor.parameters.get_all_params(),
-        );
+    if class.redirecting_constructors.is_empty() {
+        if let Some(constructor) = &class.generative_constructor {
+            generate_classic_class(output, json_output, class, constructor, context);
+        }
+        return;
     }
 
+    let (intersecting_fields, copyable_fields) =
+        shared_union_fields(context, &class.redirecting_constructors);
+
     let mixin_virtual_parameters = ParameterList {
-        positional_parameters: intersecting_fields.clone(),
+        positional_parameters: copyable_fields.clone(),
         ..Default::default()
     };
 
@@ -75,27 +419,32 @@ pub fn generate_class(output: &mut String, json_output: &mut String, class: &Cla
 
     generate_mixin(
         output,
+        context,
         &mixin_type,
         &class.name,
-        &class.mixins.first().unwrap().type_arguments,
+        &class_generics,
         &intersecting_fields,
+        !copyable_fields.is_empty(),
         &class_to_json,
+        &class.redirecting_constructors,
     );
 
-    if class.gen_form {
-        generate_introspection_class(output, &class.name, &intersecting_fields);
+    if class.has_annotation("qform") {
+        generate_introspection_class(output, &class.name, &copyable_fields);
     }
 
     generate_abstract_copywith_mixin(
         output,
+        context,
         &class.name,
         &class_generics,
         None,
         &mixin_virtual_parameters,
     );
-    if !intersecting_fields.is_empty() {
+    if !copyable_fields.is_empty() {
         generate_copywith_impl_mixin(
             output,
+            context,
             &class.name,
             &class_generics,
             &mixin_virtual_parameters,
@@ -103,17 +452,23 @@ pub fn generate_class(output: &mut String, json_output: &mut String, class: &Cla
         );
     }
 
+    // Each case of a union has its own JSON functions, told apart by the union key
+    let is_union = class.redirecting_constructors.len() > 1;
+    let supertypes = class.supertypes();
+
     for constructor in &class.redirecting_constructors {
         let inner_class = constructor.assigned_type.name.clone();
 
-        let class_to_json = if let Some(_json_constructor) = &class.json_constructor {
-            JsonMethod::Impl(class.name.clone()) // Change it to assigned json constructor? O_o
-        } else {
-            JsonMethod::None
+        let class_to_json = match &class.json_constructor {
+            Some(_) if is_union => JsonMethod::Impl(inner_class.clone()),
+            Some(_) => JsonMethod::Impl(class.name.clone()),
+            None => JsonMethod::None,
         };
 
+        generate_documentation(output, &constructor.documentation, "");
         generate_solo_class(
             output,
+            context,
             &class.name,
             &class_generics,
             class_to_json,
@@ -121,11 +476,15 @@ pub fn generate_class(output: &mut String, json_output: &mut String, class: &Cla
             &constructor.parameters,
             constructor.is_const,
             &class.unnamed_constructor,
+            &constructor.mixed_in_types(),
+            &case_interfaces(constructor, &supertypes),
+            &intersecting_fields,
         );
 
         if !constructor.parameters.is_empty() {
             generate_abstract_copywith_mixin(
                 output,
+                context,
                 &inner_class,
                 &class_generics,
                 Some(&class.name),
@@ -134,6 +493,7 @@ pub fn generate_class(output: &mut String, json_output: &mut String, class: &Cla
 
             generate_copywith_impl_mixin(
                 output,
+                context,
                 &inner_class,
                 &class_generics,
                 &constructor.parameters,
@@ -142,27 +502,218 @@ pub fn generate_class(output: &mut String, json_output: &mut String, class: &Cla
         }
     }
 
-    if class.json_constructor.is_some() {
-        let main_constructor = &class.redirecting_constructors.first().unwrap();
-        let parameters = &main_constructor.parameters;
+    let Some(from_json_function) = class.from_json_function() else {
+        return;
+    };
+    let json_options = JsonOptions::for_class(context, class);
 
-        to_json_function_generator(
+    if let [main_constructor] = class.redirecting_constructors.as_slice() {
+        generate_json_functions(
             json_output,
+            context,
             &class.name,
-            &class
-                .redirecting_constructors
-                .first()
-                .unwrap()
-                .assigned_type
-                .as_raw(),
-            &parameters.get_all_params(),
+            &json_options,
+            &main_constructor.assigned_type.as_raw(),
+            from_json_function,
+            &main_constructor.parameters,
+            &main_constructor.parameters.get_all_params(),
         );
+        return;
+    }
 
-        from_json_function_generator(
+    // `fromJson` picks the case from the union key, each case writes its own value of it
+    let freezed_options = FreezedOptions::for_class(context, class);
+    let mut cases = vec![];
+    for constructor in &class.redirecting_constructors {
+        let inner_class = &constructor.assigned_type.name;
+        let union_value = freezed_options.union_value(constructor);
+        let case_options = JsonOptions {
+            union_entry: Some((freezed_options.union_key.clone(), union_value.clone())),
+            ..json_options.clone()
+        };
+        let case_from_json_function = format!("_${inner_class}FromJson");
+
+        generate_json_functions(
             json_output,
-            &main_constructor.assigned_type.as_raw(),
+            context,
+            inner_class,
+            &case_options,
+            &constructor.assigned_type.as_raw(),
+            &case_from_json_function,
+            &constructor.parameters,
+            &constructor.parameters.get_all_params(),
+        );
+        cases.push((union_value, case_from_json_function));
+    }
+
+    if json_options.create_factory {
+        union_from_json_generator(
+            output,
             &class.name,
+            from_json_function,
+            &freezed_options.union_key,
+            &cases,
+            &json_options,
+        );
+    }
+}
+
+/// `@Implements` types of the case, then the supertypes of the class it doesn't already
+/// implement or mix in, Dart rejects a type in both clauses
+fn case_interfaces(constructor: &RedirectedConstructor, supertypes: &[DartType]) -> Vec<DartType> {
+    let mixins = constructor.mixed_in_types();
+    let mut interfaces = constructor.implemented_types();
+    for supertype in supertypes {
+        if !interfaces
+            .iter()
+            .chain(&mixins)
+            .any(|e| e.name == supertype.name)
+        {
+            interfaces.push(supertype.clone());
+        }
+    }
+    interfaces
+}
+
+/// freezed 3 "classic" classes declare their fields and a regular constructor, so only the
+/// mixin and copyWith are generated, against the fields the constructor initializes.
+fn generate_classic_class(
+    output: &mut String,
+    json_output: &mut String,
+    class: &ClassDefinition,
+    constructor: &RedirectedConstructor,
+    context: &GenerationContext,
+) {
+    let freezed_mixin = class.freezed_mixin();
+    let class_generics = freezed_mixin.type_arguments.to_owned();
+    let parameters = &constructor.parameters;
+    let fields = parameters.get_all_params();
+
+    // The class declares `toJson` itself, like with plain json_serializable classes
+    generate_mixin(
+        output,
+        context,
+        &freezed_mixin.as_raw(),
+        &class.name,
+        &class_generics,
+        &fields,
+        !fields.is_empty(),
+        &JsonMethod::None,
+        &[],
+    );
+
+    if class.has_annotation("qform") {
+        generate_introspection_class(output, &class.name, &fields);
+    }
+
+    generate_abstract_copywith_mixin(
+        output,
+        context,
+        &class.name,
+        &class_generics,
+        None,
+        parameters,
+    );
+    if !fields.is_empty() {
+        generate_copywith_impl_mixin(
+            output,
+            context,
+            &class.name,
+            &class_generics,
+            parameters,
+            true,
+        );
+    }
+
+    if let Some(from_json_function) = class.from_json_function() {
+        generate_json_functions(
+            json_output,
+            context,
+            &class.name,
+            &JsonOptions::for_class(context, class),
+            &class.name,
+            from_json_function,
+            parameters,
+            &fields,
+        );
+    }
+}
+
+/// Plain `@JsonSerializable` classes only get their JSON functions. The constructor is
+/// what `fromJson` calls, `toJson` also writes the public fields it doesn't initialize.
+fn generate_json_serializable_class(
+    json_output: &mut String,
+    class: &ClassDefinition,
+    context: &GenerationContext,
+) {
+    let Some(constructor) = &class.generative_constructor else {
+        return;
+    };
+    let parameters = &constructor.parameters;
+
+    let mut fields = parameters.get_all_params();
+    for field in &class.fields {
+        if !field.name.starts_with('_') && !fields.iter().any(|e| e.name == field.name) {
+            fields.push(field.clone());
+        }
+    }
+
+    let from_json_function = class
+        .from_json_function()
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("_${}FromJson", class.name));
+
+    generate_json_functions(
+        json_output,
+        context,
+        &class.name,
+        &JsonOptions::for_class(context, class),
+        &class.name,
+        &from_json_function,
+        parameters,
+        &fields,
+    );
+}
+
+/// `_$FooToJson`, `_$FooFromJson` and whatever else `@JsonSerializable` asks for, named
+/// after `json_name` and working on `instance_type`, the class actually holding the fields.
+/// `fields` are what `toJson` writes, `parameters` what `fromJson` passes to the constructor.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_json_functions(
+    json_output: &mut String,
+    context: &GenerationContext,
+    json_name: &str,
+    json_options: &JsonOptions,
+    instance_type: &str,
+    from_json_function: &str,
+    parameters: &ParameterList,
+    fields: &[PositionalParameter],
+) {
+    if json_options.create_to_json {
+        to_json_function_generator(
+            json_output,
+            context,
+            json_name,
+            instance_type,
+            fields,
+            json_options,
+        );
+    }
+    if json_options.create_factory {
+        from_json_function_generator(
+            json_output,
+            context,
+            instance_type,
+            from_json_function,
             parameters,
+            json_options,
         );
     }
+    if json_options.create_field_map {
+        field_map_generator(json_output, json_name, fields, json_options);
+    }
+    if json_options.create_per_field_to_json {
+        per_field_to_json_generator(json_output, context, json_name, fields, json_options);
+    }
+    enum_maps_generator(json_output, context, fields);
 }
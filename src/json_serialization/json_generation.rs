@@ -1,6 +1,285 @@
-use crate::dart_types::{Annotation, DartType, ParameterList, PositionalParameter};
+use crate::build_config::Version;
+use crate::dart_types::{
+    Annotation, ClassDefinition, DartType, ParameterList, PositionalParameter, split_top_level,
+};
+use crate::project_index::{Symbol, SymbolKind};
 use std::fmt::Write;
 
+use super::GenerationContext;
+
+/// A `JsonConverter<T, S>` instance applied to the values of type `T`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Converter {
+    /// `EpochConverter()`, as written in the annotation
+    pub instance: String,
+    /// `T`
+    pub dart_type: DartType,
+    /// `S`
+    pub json_type: DartType,
+}
+
+impl Converter {
+    /// Reads `EpochConverter()` or `const EpochConverter()`, `None` if it's not a converter
+    /// declared in the project, as only then are its types known
+    pub fn resolve(context: &GenerationContext, instance: &str) -> Option<Converter> {
+        let instance = instance.trim();
+        let instance = instance.strip_prefix("const ").unwrap_or(instance).trim();
+        let name = instance.split(['(', '<']).next()?.trim();
+
+        let mut symbol = context.resolve(name)?;
+        let constant_scope;
+        // `@epochConverter` names a constant, whose class is in the scope it's declared in
+        if let Some(value_type) = &symbol.value_type {
+            constant_scope = context.index.scope_for(&symbol.path, context.config);
+            let constant_context = GenerationContext {
+                scope: &constant_scope,
+                ..*context
+            };
+            symbol = constant_context.resolve(&value_type.name)?;
+        }
+
+        let interface = symbol.supertypes.iter().find(|e| {
+            e.name.rsplit('.').next() == Some("JsonConverter") && e.type_arguments.len() == 2
+        })?;
+        (symbol.kind == SymbolKind::Class).then(|| Converter {
+            instance: instance.to_owned(),
+            dart_type: interface.type_arguments[0].clone(),
+            json_type: interface.type_arguments[1].clone(),
+        })
+    }
+
+    /// `const EpochConverter()`, while `@epochConverter` names a constant already
+    fn expression(&self) -> String {
+        if self.instance.contains('(') {
+            format!("const {}", self.instance)
+        } else {
+            self.instance.clone()
+        }
+    }
+
+    fn applies_to(&self, dart_type: &DartType) -> bool {
+        let mut dart_type = dart_type.clone();
+        dart_type.nullable = self.dart_type.nullable;
+        dart_type == self.dart_type
+    }
+}
+
+/// How the JSON functions of a class are generated, the global defaults overridden by
+/// its `@JsonSerializable`
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonOptions {
+    /// `_$FooFromJson`, off for write-only models
+    pub create_factory: bool,
+    /// `_$FooToJson`, off for read-only models
+    pub create_to_json: bool,
+    /// `_$FooFieldMap`, field names to their JSON keys
+    pub create_field_map: bool,
+    /// `_$FooPerFieldToJson`, a static `toJson` per field for partial updates
+    pub create_per_field_to_json: bool,
+    /// Wrap the decoding in `$checkedCreate` so errors name the key that failed
+    pub checked: bool,
+    /// Accept `Map<dynamic, dynamic>`, e.g. from YAML, instead of `Map<String, dynamic>`
+    pub any_map: bool,
+    pub disallow_unrecognized_keys: bool,
+    /// Call `toJson()` on nested objects instead of leaving it to `jsonEncode`
+    pub explicit_to_json: bool,
+    /// How field names become JSON keys, unless `@JsonKey(name: ...)` says otherwise
+    pub field_rename: FieldRename,
+    /// Write `null` values, otherwise their keys are left out
+    pub include_if_null: bool,
+    /// Applied to all fields, from `@JsonSerializable(converters: [...])`
+    pub converters: Vec<Converter>,
+    /// The union key and value `toJson` of a freezed union case writes after its fields
+    pub union_entry: Option<(String, String)>,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self {
+            create_factory: true,
+            create_to_json: true,
+            create_field_map: false,
+            create_per_field_to_json: false,
+            checked: false,
+            any_map: false,
+            disallow_unrecognized_keys: false,
+            explicit_to_json: false,
+            field_rename: FieldRename::None,
+            include_if_null: true,
+            converters: Vec::new(),
+            union_entry: None,
+        }
+    }
+}
+
+impl JsonOptions {
+    pub fn for_class(context: &GenerationContext, class: &ClassDefinition) -> JsonOptions {
+        let mut options = context.options.json.clone();
+        let flag = |name: &str, default: bool| match class.get_json_serializable_argument(name) {
+            Some(value) => value == "true",
+            None => default,
+        };
+        options.create_factory = flag("createFactory", options.create_factory);
+        options.create_to_json = flag("createToJson", options.create_to_json);
+        options.create_field_map = flag("createFieldMap", options.create_field_map);
+        options.create_per_field_to_json =
+            flag("createPerFieldToJson", options.create_per_field_to_json);
+        options.checked = flag("checked", options.checked);
+        options.any_map = flag("anyMap", options.any_map);
+        options.disallow_unrecognized_keys = flag(
+            "disallowUnrecognizedKeys",
+            options.disallow_unrecognized_keys,
+        );
+        options.explicit_to_json = flag("explicitToJson", options.explicit_to_json);
+        options.include_if_null = flag("includeIfNull", options.include_if_null);
+        if let Some(field_rename) = class
+            .get_json_serializable_argument("fieldRename")
+            .and_then(|e| FieldRename::parse(e.trim_start_matches("FieldRename.")))
+        {
+            options.field_rename = field_rename;
+        }
+
+        if let Some(list) = class.get_json_serializable_argument("converters") {
+            let list = list.trim_start_matches("const").trim();
+            let list = list.trim_start_matches('[').trim_end_matches(']');
+            options.converters.extend(
+                split_top_level(list)
+                    .into_iter()
+                    .filter(|e| !e.is_empty())
+                    .filter_map(|e| Converter::resolve(context, e)),
+            );
+        }
+
+        options
+    }
+}
+
+fn field_converter(context: &GenerationContext, annotation: &Annotation) -> Option<Converter> {
+    let instance = annotation.source.trim_start_matches('@');
+    Converter::resolve(context, instance)
+}
+
+/// Field converters first, they win over the class ones
+fn field_converters(
+    context: &GenerationContext,
+    annotations: &[Annotation],
+    class_converters: &[Converter],
+) -> Vec<Converter> {
+    annotations
+        .iter()
+        .filter_map(|e| field_converter(context, e))
+        .chain(class_converters.iter().cloned())
+        .collect()
+}
+
+fn find_converter<'a>(
+    converters: &'a [Converter],
+    dart_type: &DartType,
+    expanded_type: &DartType,
+) -> Option<&'a Converter> {
+    converters
+        .iter()
+        .find(|e| e.applies_to(dart_type) || e.applies_to(expanded_type))
+}
+
+/// `FieldRename`, the `fieldRename` of `@JsonSerializable`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FieldRename {
+    #[default]
+    None,
+    Kebab,
+    Snake,
+    Pascal,
+    ScreamingSnake,
+}
+
+impl FieldRename {
+    /// `snake` from `FieldRename.snake` or `field_rename: snake`
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "none" => Some(Self::None),
+            "kebab" => Some(Self::Kebab),
+            "snake" => Some(Self::Snake),
+            "pascal" => Some(Self::Pascal),
+            // `screaming_snake` is how build.yaml spells it
+            "screamingSnake" | "screaming_snake" => Some(Self::ScreamingSnake),
+            _ => None,
+        }
+    }
+
+    /// The JSON key for a field called `name`, `fooBar` being `foo_bar` in snake case
+    pub fn apply(self, name: &str) -> String {
+        let separated = |separator: char| {
+            let mut key = String::with_capacity(name.len() + 4);
+            for (i, c) in name.char_indices() {
+                if c.is_ascii_uppercase() && i > 0 {
+                    key.push(separator);
+                }
+                key.push(c.to_ascii_lowercase());
+            }
+            key
+        };
+
+        match self {
+            Self::None => name.to_owned(),
+            Self::Kebab => separated('-'),
+            Self::Snake => separated('_'),
+            Self::ScreamingSnake => separated('_').to_ascii_uppercase(),
+            Self::Pascal => {
+                let mut chars = name.chars();
+                chars
+                    .next()
+                    .map(|e| e.to_ascii_uppercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// The key `parameter` is read from and written to
+fn json_key(parameter: &PositionalParameter, options: &JsonOptions) -> String {
+    match json_key_argument(&parameter.annotations, "name") {
+        Some(name) => name.trim_matches(['\'', '"']).to_owned(),
+        None => options.field_rename.apply(&parameter.name),
+    }
+}
+
+/// `fromJson` of a freezed union, handing `json` to the case its union key names
+pub fn union_from_json_generator(
+    output: &mut String,
+    class_name: &str,
+    function_name: &str,
+    union_key: &str,
+    cases: &[(String, String)],
+    options: &JsonOptions,
+) {
+    let json_type = if options.any_map {
+        "Map"
+    } else {
+        "Map<String, dynamic>"
+    };
+
+    let _ = writeln!(output);
+    let _ = writeln!(output, "{class_name} {function_name}({json_type} json) {{");
+    let _ = writeln!(output, "  switch (json['{union_key}']) {{");
+    for (value, case_function) in cases {
+        let _ = writeln!(output, "    case '{value}':");
+        let _ = writeln!(output, "      return {case_function}(json);");
+    }
+    let _ = writeln!(output, "    default:");
+    let _ = writeln!(output, "      throw CheckedFromJsonException(");
+    let _ = writeln!(output, "        json,");
+    let _ = writeln!(output, "        '{union_key}',");
+    let _ = writeln!(output, "        '{class_name}',");
+    let _ = writeln!(
+        output,
+        "        'Invalid union type \"${{json['{union_key}']}}\"!',"
+    );
+    let _ = writeln!(output, "      );");
+    let _ = writeln!(output, "  }}");
+    let _ = writeln!(output, "}}");
+}
+
 pub fn from_json_method_generator(output: &mut String, class_name: &str, from_json_name: &str) {
     let _ = writeln!(
         output,
@@ -11,82 +290,234 @@ pub fn from_json_method_generator(output: &mut String, class_name: &str, from_js
 
 pub fn from_json_function_generator(
     output: &mut String,
+    context: &GenerationContext,
     class_name: &str,
-    from_json_name: &str,
+    function_name: &str,
     parameters: &ParameterList,
+    options: &JsonOptions,
 ) {
-    let _ = writeln!(
-        output,
-        "{class_name} _${from_json_name}FromJson(Map<String, dynamic> json) =>"
-    );
+    let check_keys = check_keys_call(parameters, options);
+    let json_type = if options.any_map {
+        "Map"
+    } else {
+        "Map<String, dynamic>"
+    };
 
-    let _ = writeln!(output, "{class_name}(");
-    for parameter in &parameters.positional_parameters {
-        let from_item = format!("json['{0}']", parameter.name);
+    if options.checked {
+        let _ = writeln!(
+            output,
+            "{class_name} {function_name}({json_type} json) => $checkedCreate(
+  '{class_name}',
+  json,
+  ($checkedConvert) {{"
+        );
+        if let Some(check_keys) = &check_keys {
+            let _ = writeln!(output, "{check_keys}");
+        }
+        let _ = writeln!(output, "final val = {class_name}(");
+        from_json_arguments(output, context, parameters, options);
+        let _ = writeln!(output, ");");
+        let _ = writeln!(output, "return val;");
+        let _ = writeln!(output, "  }},");
+        let _ = writeln!(output, ");");
+    } else if let Some(check_keys) = &check_keys {
+        let _ = writeln!(output, "{class_name} {function_name}({json_type} json) {{");
+        let _ = writeln!(output, "{check_keys}");
+        let _ = writeln!(output, "return {class_name}(");
+        from_json_arguments(output, context, parameters, options);
+        let _ = writeln!(output, ");");
+        let _ = writeln!(output, "}}");
+    } else {
+        let _ = writeln!(output, "{class_name} {function_name}({json_type} json) =>");
+        let _ = writeln!(output, "{class_name}(");
+        from_json_arguments(output, context, parameters, options);
+        let _ = writeln!(output, ");",);
+    }
+}
+
+/// `value` of `@JsonKey(name: value)`
+fn json_key_argument<'a>(annotations: &'a [Annotation], name: &str) -> Option<&'a str> {
+    annotations
+        .iter()
+        .filter(|e| e.name == "JsonKey")
+        .find_map(|e| e.get_named_argument(name))
+}
+
+/// `$checkKeys(json, ...)` when keys are restricted by the class or its `@JsonKey`s
+fn check_keys_call(parameters: &ParameterList, options: &JsonOptions) -> Option<String> {
+    let all_params = parameters.get_all_params();
+    let json_key_flag = |parameter: &PositionalParameter, name: &str| {
+        json_key_argument(&parameter.annotations, name) == Some("true")
+    };
+    let keys = |filter: &dyn Fn(&PositionalParameter) -> bool| {
+        let keys: Vec<String> = all_params
+            .iter()
+            .filter(|e| filter(e))
+            .map(|e| format!("'{}'", json_key(e, options)))
+            .collect();
+        keys.join(", ")
+    };
+
+    let mut arguments = Vec::new();
+    if options.disallow_unrecognized_keys {
+        // The union key picked this case, so it's always there
+        let mut allowed_keys = keys(&|_| true);
+        if let Some((union_key, _)) = &options.union_entry {
+            if !allowed_keys.is_empty() {
+                allowed_keys.push_str(", ");
+            }
+            allowed_keys.push_str(&format!("'{union_key}'"));
+        }
+        arguments.push(format!("allowedKeys: const [{allowed_keys}]"));
+    }
+    let required_keys = keys(&|e| json_key_flag(e, "required"));
+    if !required_keys.is_empty() {
+        arguments.push(format!("requiredKeys: const [{required_keys}]"));
+    }
+    let disallow_null_values = keys(&|e| json_key_flag(e, "disallowNullValue"));
+    if !disallow_null_values.is_empty() {
+        arguments.push(format!(
+            "disallowNullValues: const [{disallow_null_values}]"
+        ));
+    }
+
+    if arguments.is_empty() {
+        return None;
+    }
+    Some(format!("  $checkKeys(json, {});", arguments.join(", ")))
+}
+
+/// The constructor arguments, each decoded from its key or, in checked mode, through
+/// `$checkedConvert` so a failure reports the key
+fn from_json_arguments(
+    output: &mut String,
+    context: &GenerationContext,
+    parameters: &ParameterList,
+    options: &JsonOptions,
+) {
+    let mut argument = |label: Option<&str>, parameter: &PositionalParameter, default| {
+        if let Some(label) = label {
+            let _ = writeln!(output, "{label}: ");
+        }
+        let read_value = json_key_argument(&parameter.annotations, "readValue");
+        let key = json_key(parameter, options);
+        let from_item = if options.checked {
+            let _ = write!(output, "$checkedConvert('{key}', (v) => ");
+            "v".to_owned()
+        } else if let Some(read_value) = read_value {
+            format!("{read_value}(json, '{key}')")
+        } else {
+            format!("json['{key}']")
+        };
         from_json_field_gen(
             output,
+            context,
+            options,
             &from_item,
             &parameter.dart_type,
             &parameter.annotations,
+            default,
         );
+        if options.checked {
+            if let Some(read_value) = read_value {
+                let _ = write!(output, ", readValue: {read_value}");
+            }
+            let _ = write!(output, ")");
+        }
         let _ = writeln!(output, ", ");
-    }
+    };
 
-    if !parameters.named_parameters.is_empty() {
-        for parameter in &parameters.named_parameters {
-            let from_item = format!("json['{0}']", parameter.name);
-            let _ = writeln!(output, "{}: ", parameter.name);
-            from_json_field_gen(
-                output,
-                &from_item,
-                &parameter.dart_type,
-                &parameter.annotations,
-            );
-            let _ = writeln!(output, ", ");
-        }
+    for parameter in &parameters.positional_parameters {
+        argument(None, parameter, None);
     }
 
-    let _ = writeln!(output, ");",);
+    for parameter in &parameters.named_parameters {
+        argument(
+            Some(&parameter.name),
+            &parameter.to_positional(),
+            parameter.default.as_deref(),
+        );
+    }
 }
 
 fn from_json_field_gen(
     output: &mut String,
+    context: &GenerationContext,
+    options: &JsonOptions,
     from_item: &str,
     dart_type: &DartType,
     annotations: &[Annotation],
+    constructor_default: Option<&str>,
 ) {
+    let declared_type = dart_type;
+    let dart_type = &context.expand_type(dart_type);
+    let field_converters = field_converters(context, annotations, &options.converters);
+    // They keep applying to the elements
+    let options = &JsonOptions {
+        converters: field_converters,
+        ..options.clone()
+    };
+    let map_type = if options.any_map {
+        "Map"
+    } else {
+        "Map<String, dynamic>"
+    };
     let mut is_nullable = dart_type.nullable;
     let mut default_value: String = String::new();
 
-    if let Some(default) = annotations.iter().find(|e| e.name == "Default") {
-        default_value = format!(" ?? {}", default.get_default_value());
+    if let Some(default) = annotations.iter().find_map(Annotation::get_default_value) {
+        default_value = format!(" ?? {default}");
+        is_nullable = true;
+    } else if let Some(default) = json_key_argument(annotations, "defaultValue") {
+        // Used when the key is missing or null, like `@Default`
+        default_value = format!(" ?? {default}");
+        is_nullable = true;
+    } else if let Some(default) = constructor_default {
+        // `{this.count = 0}` in classes declaring their own constructor
+        default_value = format!(" ?? {default}");
         is_nullable = true;
     }
 
-    if let Some(converter) = annotations
-        .iter()
-        .find(|e| e.name.ends_with("JsonConverter"))
-    {
+    if let Some(converter) = find_converter(&options.converters, declared_type, dart_type) {
+        let cast = format!(" as {}", converter.json_type.as_raw());
+        let instance = converter.expression();
+        if is_nullable {
+            let _ = writeln!(
+                output,
+                "({from_item} == null ? null : {instance}.fromJson({from_item}{cast}))"
+            );
+        } else {
+            let _ = writeln!(output, "{instance}.fromJson({from_item}{cast})");
+        }
+    } else if let Some(representation) = context.representation(dart_type) {
+        let mut inner_output = String::new();
+        from_json_field_gen(
+            &mut inner_output,
+            context,
+            options,
+            from_item,
+            &representation.dart_type,
+            &[],
+            None,
+        );
+        let inner_output = inner_output.trim_end();
+
+        let constructor = &representation.constructor;
         if is_nullable {
             let _ = writeln!(
                 output,
-                "
-                ({from_item} == null ? null : const {0}().fromJson({from_item}))
-                ",
-                converter.name
+                "({from_item} == null ? null : {constructor}({inner_output}))"
             );
         } else {
-            let _ = writeln!(output, "const {0}().fromJson({from_item})", converter.name);
+            let _ = writeln!(output, "{constructor}({inner_output})");
         }
     } else {
         let nullable = if is_nullable { "?" } else { "" };
         match dart_type.name.as_str() {
             "int" => {
-                assert!(dart_type.type_arguments.is_empty());
                 let _ = writeln!(output, "({from_item} as num{nullable}){nullable}.toInt()");
             }
             "double" => {
-                assert!(dart_type.type_arguments.is_empty());
                 let _ = writeln!(
                     output,
                     "({from_item} as num{nullable}){nullable}.toDouble()"
@@ -94,56 +525,110 @@ fn from_json_field_gen(
             }
 
             "bool" => {
-                assert!(dart_type.type_arguments.is_empty());
                 let _ = writeln!(output, "(({from_item}) as bool{nullable})");
             }
             "String" => {
-                assert!(dart_type.type_arguments.is_empty());
                 let _ = writeln!(output, "(({from_item}) as String{nullable})");
             }
-            "DateTime" => {
-                assert!(dart_type.type_arguments.is_empty());
-                eprintln!("Warning! DateTime was used without a decorator.");
-                eprintln!("{from_item}");
+            "num" | "Object" => {
+                let _ = writeln!(output, "({from_item} as {0}{nullable})", dart_type.name);
+            }
+            "DateTime" | "Uri" | "BigInt" | "Duration" => {
+                let parsed = match dart_type.name.as_str() {
+                    "Duration" => format!("Duration(microseconds: ({from_item} as num).toInt())"),
+                    name => format!("{name}.parse({from_item} as String)"),
+                };
                 if is_nullable {
+                    let _ = writeln!(output, "({from_item} == null ? null : {parsed})");
+                } else {
+                    let _ = writeln!(output, "{parsed}");
+                }
+            }
+            "Iterable" | "List" => {
+                let element_type = dart_type
+                    .type_arguments
+                    .first()
+                    .cloned()
+                    .unwrap_or_default();
+                let element = element_from_json(context, options, &element_type);
+                let to_list = if dart_type.name == "List" {
+                    ".toList()"
+                } else {
+                    ""
+                };
+
+                if element == "e" && to_list.is_empty() {
+                    let _ = writeln!(output, "({from_item} as List<dynamic>{nullable})");
+                } else {
                     let _ = writeln!(
                         output,
-                        "({from_item} == null ? null : DateTime.parse({from_item} as String))"
+                        "({from_item} as List<dynamic>{nullable}){nullable}.map(
+    (e) => {element} ){to_list}"
                     );
+                }
+            }
+
+            "Map" => {
+                let value_type = dart_type.type_arguments.get(1).cloned().unwrap_or_default();
+                let value = element_from_json(context, options, &value_type);
+
+                let key = if options.any_map { "k as String" } else { "k" };
+
+                if value == "e" && !options.any_map {
+                    let _ = writeln!(output, "({from_item} as Map<String, dynamic>{nullable})");
                 } else {
-                    let _ = writeln!(output, "DateTime.parse({from_item} as String)");
+                    let _ = writeln!(
+                        output,
+                        "({from_item} as {map_type}{nullable}){nullable}.map(
+    (k, e) => MapEntry({key}, {value}))"
+                    );
                 }
             }
-            "List" => {
-                assert_eq!(dart_type.type_arguments.len(), 1);
-                let mut inner_output = String::new();
-                let inner_type = dart_type.type_arguments.first().unwrap();
-                from_json_field_gen(&mut inner_output, "e", inner_type, &[]);
 
+            "" | "dynamic" => {
+                let _ = writeln!(output, "{from_item}");
+            }
+
+            _ if context.is_enum(dart_type) => {
+                let decode = if is_nullable {
+                    "$enumDecodeNullable"
+                } else {
+                    "$enumDecode"
+                };
                 let _ = writeln!(
                     output,
-                    "({from_item} as List<dynamic>{nullable}){nullable}.map(
-    (e) => {0} ).toList()",
-                    &inner_output
+                    "{decode}({0}, {from_item})",
+                    enum_map_name(dart_type)
                 );
             }
 
-            "" | "dynamic" => {
-                let _ = writeln!(output, "{from_item}");
+            // An extension type's own `fromJson` declares what it takes, and the dynamic
+            // value is assignable to any parameter type
+            _ if context.extension_type(dart_type).is_some() => {
+                let mut name_not_null = dart_type.clone();
+                name_not_null.nullable = false;
+                let parsed = format!("{0}.fromJson({from_item})", name_not_null.as_raw());
+                if is_nullable {
+                    let _ = writeln!(output, "({from_item} == null ? null : {parsed})");
+                } else {
+                    let _ = writeln!(output, "{parsed}");
+                }
             }
 
             // If there's no pattern matched, then assume this is an object with .fromJson method
             _ => {
-                if dart_type.nullable {
-                    let mut name_not_null = dart_type.clone();
-                    name_not_null.nullable = false;
-                    let _ = writeln!(
-                        output,
-                        "{from_item} == null ? null : {0}.fromJson({from_item})",
-                        name_not_null.as_raw()
-                    );
+                let mut name_not_null = dart_type.clone();
+                name_not_null.nullable = false;
+                let json = if options.any_map {
+                    format!("Map<String, dynamic>.from({from_item} as Map)")
+                } else {
+                    format!("{from_item} as Map<String, dynamic>")
+                };
+                let parsed = format!("{0}.fromJson({json})", name_not_null.as_raw());
+                if is_nullable {
+                    let _ = writeln!(output, "({from_item} == null ? null : {parsed})");
                 } else {
-                    let _ = writeln!(output, "{0}.fromJson({from_item})", dart_type.as_raw());
+                    let _ = writeln!(output, "{parsed}");
                 }
             }
         }
@@ -152,6 +637,17 @@ fn from_json_field_gen(
     let _ = writeln!(output, "{default_value}");
 }
 
+/// Decoding of one `e` element of a collection, with its own nullability
+fn element_from_json(
+    context: &GenerationContext,
+    options: &JsonOptions,
+    element_type: &DartType,
+) -> String {
+    let mut element = String::new();
+    from_json_field_gen(&mut element, context, options, "e", element_type, &[], None);
+    element.trim().to_owned()
+}
+
 pub fn to_json_method_generator(output: &mut String, class_name: Option<&str>) {
     if let Some(class_name) = class_name {
         let _ = writeln!(output, "  @override");
@@ -165,61 +661,279 @@ pub fn to_json_method_generator(output: &mut String, class_name: Option<&str>) {
 
 pub fn to_json_function_generator(
     output: &mut String,
+    context: &GenerationContext,
     to_json_name: &str,
     class_name: &str,
     fields: &[PositionalParameter],
+    options: &JsonOptions,
 ) {
+    let union_entry = options
+        .union_entry
+        .iter()
+        .map(|(key, value)| (key.clone(), format!("'{value}'"), false));
+    let entries: Vec<_> = fields
+        .iter()
+        .map(|e| to_json_entry(context, options, e))
+        .chain(union_entry)
+        .collect();
+
+    // Before 6.8 null values were left out through a `writeNotNull` helper, not `case`
+    let legacy_null_checks = context
+        .options
+        .json_serializable_version
+        .is_some_and(|e| e < Version::new(6, 8, 0));
+    if legacy_null_checks && let Some(first_checked) = entries.iter().position(|e| e.2) {
+        let _ = writeln!(
+            output,
+            "Map<String, dynamic> _${to_json_name}ToJson({class_name} instance) {{"
+        );
+        let _ = writeln!(output, "  final val = <String, dynamic>{{");
+        for (key, json_value, _) in &entries[..first_checked] {
+            let _ = writeln!(output, "    '{key}': {json_value},");
+        }
+        let _ = writeln!(output, "  }};");
+        let _ = writeln!(output);
+        let _ = writeln!(output, "  void writeNotNull(String key, dynamic value) {{");
+        let _ = writeln!(output, "    if (value != null) {{");
+        let _ = writeln!(output, "      val[key] = value;");
+        let _ = writeln!(output, "    }}");
+        let _ = writeln!(output, "  }}");
+        let _ = writeln!(output);
+        for (key, json_value, skip_null) in &entries[first_checked..] {
+            if *skip_null {
+                let _ = writeln!(output, "  writeNotNull('{key}', {json_value});");
+            } else {
+                let _ = writeln!(output, "  val['{key}'] = {json_value};");
+            }
+        }
+        let _ = writeln!(output, "  return val;");
+        let _ = writeln!(output, "}}");
+        return;
+    }
+
     let _ = writeln!(
         output,
         "Map<String, dynamic> _${to_json_name}ToJson({class_name} instance) =>"
     );
     let _ = writeln!(output, "    <String, dynamic>{{");
 
-    for parameter in fields {
-        let _ = write!(output, "    ");
-        to_json_field_gen(output, parameter);
-        let _ = writeln!(output, ",");
+    for (key, json_value, skip_null) in &entries {
+        if *skip_null {
+            let _ = writeln!(
+                output,
+                "    if ({json_value} case final value?) '{key}': value,"
+            );
+        } else {
+            let _ = writeln!(output, "    '{key}': {json_value},");
+        }
     }
 
     let _ = writeln!(output, "    }};");
 }
 
-fn to_json_field_gen(output: &mut String, parameter: &PositionalParameter) {
-    let _ = write!(output, "'{}': ", parameter.name);
+/// The key, the value and whether it's left out when `null`
+fn to_json_entry(
+    context: &GenerationContext,
+    options: &JsonOptions,
+    parameter: &PositionalParameter,
+) -> (String, String, bool) {
+    let key = json_key(parameter, options);
+    let options = &JsonOptions {
+        converters: field_converters(context, &parameter.annotations, &options.converters),
+        ..options.clone()
+    };
+    let value = format!("instance.{}", parameter.name);
+    let json_value = to_json_value(context, options, &value, &parameter.dart_type);
 
-    if let Some(converter) = parameter
-        .annotations
-        .iter()
-        .find(|e| e.name.ends_with("JsonConverter"))
-    {
-        if parameter.dart_type.nullable {
-            let _ = writeln!(
-                output,
-                "
-                (instance.{0} == null ? null : const {1}().toJson(instance.{0}!))
-                ",
-                parameter.name, converter.name
-            );
-        } else {
-            let _ = writeln!(
-                output,
-                "const {1}().toJson(instance.{0})",
-                parameter.name, converter.name
-            );
-        }
-    } else if parameter.dart_type.name == "DateTime" {
-        let nullable = if parameter.dart_type.nullable {
-            "?"
+    let include_if_null = match json_key_argument(&parameter.annotations, "includeIfNull") {
+        Some(flag) => flag == "true",
+        None => options.include_if_null,
+    };
+    let dart_type = context.expand_type(&parameter.dart_type);
+    let skip_null = !include_if_null && (dart_type.nullable || dart_type.name == "dynamic");
+    (key, json_value, skip_null)
+}
+
+/// Expression turning `value` into its JSON representation, `value` itself when
+/// `jsonEncode` already knows how to handle it
+fn to_json_value(
+    context: &GenerationContext,
+    options: &JsonOptions,
+    value: &str,
+    dart_type: &DartType,
+) -> String {
+    let declared_type = dart_type;
+    let dart_type = &context.expand_type(dart_type);
+    let nullable = if dart_type.nullable { "?" } else { "" };
+
+    if let Some(converter) = find_converter(&options.converters, declared_type, dart_type) {
+        let instance = converter.expression();
+        return if dart_type.nullable {
+            format!("{value} == null ? null : {instance}.toJson({value}!)")
         } else {
-            ""
+            format!("{instance}.toJson({value})")
         };
+    }
+
+    if let Some(symbol) = context.extension_type(dart_type) {
+        if symbol.has_to_json {
+            return format!("{value}{nullable}.toJson()");
+        }
+        if let Some(representation) = &symbol.representation {
+            let mut wrapped_type = representation.dart_type.clone();
+            wrapped_type.nullable |= dart_type.nullable;
+            let wrapped_value = format!("{value}{nullable}.{}", representation.field);
+            return to_json_value(context, options, &wrapped_value, &wrapped_type);
+        }
+    }
+
+    match dart_type.name.as_str() {
+        "DateTime" => format!("{value}{nullable}.toIso8601String()"),
+        "Duration" => format!("{value}{nullable}.inMicroseconds"),
+        "Uri" | "BigInt" => format!("{value}{nullable}.toString()"),
+        "Iterable" => {
+            let Some(inner_type) = dart_type.type_arguments.first() else {
+                return format!("{value}{nullable}.toList()");
+            };
+            let inner_value = to_json_value(context, options, "e", inner_type);
+            if inner_value == "e" {
+                format!("{value}{nullable}.toList()")
+            } else {
+                format!("{value}{nullable}.map((e) => {inner_value}).toList()")
+            }
+        }
+        "List" => {
+            let Some(inner_type) = dart_type.type_arguments.first() else {
+                return value.to_owned();
+            };
+            let inner_value = to_json_value(context, options, "e", inner_type);
+            if inner_value == "e" {
+                value.to_owned()
+            } else {
+                format!("{value}{nullable}.map((e) => {inner_value}).toList()")
+            }
+        }
+        "Map" => {
+            let Some(value_type) = dart_type.type_arguments.get(1) else {
+                return value.to_owned();
+            };
+            let inner_value = to_json_value(context, options, "e", value_type);
+            if inner_value == "e" {
+                value.to_owned()
+            } else {
+                format!("{value}{nullable}.map((k, e) => MapEntry(k, {inner_value}))")
+            }
+        }
+        _ if context.is_enum(dart_type) => {
+            let non_null = if dart_type.nullable { "" } else { "!" };
+            format!("{0}[{value}]{non_null}", enum_map_name(dart_type))
+        }
+        _ if options.explicit_to_json
+            && context
+                .resolve(&dart_type.name)
+                .is_some_and(|e| e.kind == SymbolKind::Class && e.has_to_json) =>
+        {
+            format!("{value}{nullable}.toJson()")
+        }
+        _ => value.to_owned(),
+    }
+}
+
+/// `const _$FooFieldMap = {'fooBar': 'foo_bar'};`
+pub fn field_map_generator(
+    output: &mut String,
+    class_name: &str,
+    fields: &[PositionalParameter],
+    options: &JsonOptions,
+) {
+    let _ = writeln!(output);
+    let _ = writeln!(output, "const _${class_name}FieldMap = <String, String>{{");
+    for field in fields {
+        let _ = writeln!(
+            output,
+            "  '{}': '{}',",
+            field.name,
+            json_key(field, options)
+        );
+    }
+    let _ = writeln!(output, "}};");
+}
 
-        let _ = write!(
+/// A static `toJson` per field, for the updates that only send some of them
+pub fn per_field_to_json_generator(
+    output: &mut String,
+    context: &GenerationContext,
+    class_name: &str,
+    fields: &[PositionalParameter],
+    options: &JsonOptions,
+) {
+    let _ = writeln!(output);
+    let _ = writeln!(output, "// ignore: unused_element");
+    let _ = writeln!(output, "abstract class _${class_name}PerFieldToJson {{");
+    for field in fields {
+        let options = &JsonOptions {
+            converters: field_converters(context, &field.annotations, &options.converters),
+            ..options.clone()
+        };
+        let _ = writeln!(output, "  // ignore: unused_element");
+        let _ = writeln!(
             output,
-            "instance.{0}{nullable}.toIso8601String()",
-            parameter.name
+            "  static Object? {0}({1} instance) => {2};",
+            field.name,
+            field.dart_type.as_raw(),
+            to_json_value(context, options, "instance", &field.dart_type)
         );
-    } else {
-        let _ = write!(output, "instance.{0}", parameter.name);
+    }
+    let _ = writeln!(output, "}}");
+}
+
+/// `_$StatusEnumMap`, also for `p.Status` imported with a prefix
+fn enum_map_name(dart_type: &DartType) -> String {
+    let name = dart_type.name.rsplit('.').next().unwrap_or_default();
+    format!("_${name}EnumMap")
+}
+
+/// `_$StatusEnumMap` for every enum the fields use, unless the file already has it
+pub fn enum_maps_generator(
+    output: &mut String,
+    context: &GenerationContext,
+    fields: &[PositionalParameter],
+) {
+    fn collect_enums<'a, 'b>(
+        context: &GenerationContext<'a>,
+        dart_type: &'b DartType,
+        enums: &mut Vec<(&'b DartType, &'a Symbol)>,
+    ) {
+        if context.is_enum(dart_type)
+            && let Some(symbol) = context.resolve(&dart_type.name)
+        {
+            enums.push((dart_type, symbol));
+        }
+        for type_argument in &dart_type.type_arguments {
+            collect_enums(context, type_argument, enums);
+        }
+    }
+
+    let expanded_types: Vec<DartType> = fields
+        .iter()
+        .map(|e| context.expand_type(&e.dart_type))
+        .collect();
+    let mut enums = Vec::new();
+    for dart_type in &expanded_types {
+        collect_enums(context, dart_type, &mut enums);
+    }
+
+    for (dart_type, symbol) in enums {
+        let declaration = format!("const {} = {{", enum_map_name(dart_type));
+        if output.contains(&declaration) {
+            continue;
+        }
+
+        let _ = writeln!(output);
+        let _ = writeln!(output, "{declaration}");
+        for (constant, json_value) in &symbol.enum_values {
+            let _ = writeln!(output, "  {0}.{constant}: {json_value},", dart_type.name);
+        }
+        let _ = writeln!(output, "}};");
     }
 }
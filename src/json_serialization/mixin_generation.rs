@@ -1,24 +1,39 @@
 use std::fmt::Write;
 
-use crate::dart_types::{DartType, PositionalParameter, get_generic_string};
+use crate::dart_types::{DartType, PositionalParameter, RedirectedConstructor, get_generic_string};
 
-use super::{JsonMethod, generate_mixin_copywith_function, to_json_method_generator};
+use super::{
+    FreezedVersion, GenerationContext, JsonMethod, generate_mixin_copywith_function,
+    to_json_method_generator,
+};
 
+/// freezed 2 classes may be concrete, so the mixin can't leave members abstract. The
+/// error is declared once per `.freezed.dart`.
+pub const PRIVATE_CONSTRUCTOR_ERROR: &str = "
+final _privateConstructorUsedError = UnsupportedError(
+    'It seems like you constructed your class using `MyClass._()`. This constructor is only meant to be used by freezed and you are not supposed to need it nor use it.\\nPlease check the documentation here for more information: https://github.com/rrousselGit/freezed#adding-getters-and-methods-to-our-models');
+";
+
+#[allow(clippy::too_many_arguments)]
 pub fn generate_mixin(
     output: &mut String,
+    context: &GenerationContext,
     mixin_type: &str,
     class_name: &str,
     class_generics: &[DartType],
     fields: &[PositionalParameter],
+    has_copy_with: bool,
     class_to_json: &JsonMethod,
+    cases: &[RedirectedConstructor],
 ) {
+    let is_v2 = context.options.freezed_version == FreezedVersion::V2;
     let _ = writeln!(output, "/// @nodoc");
     let _ = writeln!(output, "mixin {mixin_type} {{");
-    generate_mixin_getters(output, fields);
+    generate_mixin_getters(output, context, fields);
     let _ = writeln!(output);
-    generate_eq_operator(output, mixin_type, fields);
+    generate_eq_operator(output, context, mixin_type, fields);
     let _ = writeln!(output);
-    generate_hash_operator(output, fields);
+    generate_hash_operator(output, context, fields);
     let _ = writeln!(output);
 
     let mut copywith_generics = class_generics.to_owned();
@@ -36,15 +51,27 @@ pub fn generate_mixin(
     let just_generics = get_generic_string(class_generics);
     let copywith_generics = get_generic_string(&copywith_generics);
 
-    if !fields.is_empty() {
+    if has_copy_with {
         generate_mixin_copywith_function(output, class_name, &copywith_generics, &just_generics);
         let _ = writeln!(output);
     }
     generate_to_string(output, class_name, fields, false);
     let _ = writeln!(output);
 
+    // freezed 3 leaves it to `switch`
+    if is_v2 && cases.len() > 1 {
+        generate_when_map(output, class_generics, cases);
+    }
+
     match class_to_json {
         JsonMethod::None => {}
+        JsonMethod::Signature if is_v2 => {
+            let _ = writeln!(
+                output,
+                "  Map<String, dynamic> toJson() => throw _privateConstructorUsedError;"
+            );
+            let _ = writeln!(output);
+        }
         JsonMethod::Signature => {
             to_json_method_generator(output, None);
             let _ = writeln!(output);
@@ -58,9 +85,129 @@ pub fn generate_mixin(
     let _ = writeln!(output, "}}");
 }
 
-pub fn generate_mixin_getters(output: &mut String, fields: &[PositionalParameter]) {
+pub fn generate_mixin_getters(
+    output: &mut String,
+    context: &GenerationContext,
+    fields: &[PositionalParameter],
+) {
+    let body = match context.options.freezed_version {
+        FreezedVersion::V2 => " => throw _privateConstructorUsedError",
+        FreezedVersion::V3 => "",
+    };
     for field in fields {
-        let _ = writeln!(output, "  {} get {};", field.dart_type.as_raw(), field.name);
+        generate_documentation(output, &field.documentation, "  ");
+        let _ = writeln!(
+            output,
+            "  {} get {}{body};",
+            field.dart_type.as_raw(),
+            field.name
+        );
+    }
+}
+
+/// freezed 2's `when`, `whenOrNull`, `maybeWhen` and their `map` counterparts, named after
+/// the constructors, `$default` for the unnamed one. They test which case `this` is, so
+/// the cases themselves don't need to implement them.
+fn generate_when_map(
+    output: &mut String,
+    class_generics: &[DartType],
+    cases: &[RedirectedConstructor],
+) {
+    let just_generics = get_generic_string(class_generics);
+    let case_name = |case: &RedirectedConstructor| {
+        case.constructor_name
+            .clone()
+            .unwrap_or_else(|| "$default".to_owned())
+    };
+    let case_type =
+        |case: &RedirectedConstructor| format!("{}{just_generics}", case.assigned_type.name);
+
+    // (method, result type, callbacks are required, has orElse, maps the whole case)
+    let methods = [
+        ("when", "TResult", true, false, false),
+        ("whenOrNull", "TResult?", false, false, false),
+        ("maybeWhen", "TResult", false, true, false),
+        ("map", "TResult", true, false, true),
+        ("mapOrNull", "TResult?", false, false, true),
+        ("maybeMap", "TResult", false, true, true),
+    ];
+
+    for (method, result, required, or_else, maps) in methods {
+        let _ = writeln!(output, "  @optionalTypeArgs");
+        let _ = writeln!(output, "  {result} {method}<TResult extends Object?>({{");
+        for case in cases {
+            let parameters = if maps {
+                format!("{} value", case_type(case))
+            } else {
+                case.parameters
+                    .get_all_params()
+                    .iter()
+                    .map(|e| format!("{} {}", e.dart_type.as_raw(), e.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let (required, optional) = if required {
+                ("required ", "")
+            } else {
+                ("", "?")
+            };
+            let _ = writeln!(
+                output,
+                "    {required}{result} Function({parameters}){optional} {},",
+                case_name(case)
+            );
+        }
+        if or_else {
+            let _ = writeln!(output, "    required TResult orElse(),");
+        }
+        let _ = writeln!(output, "  }}) {{");
+        let _ = writeln!(output, "    final self = this;");
+
+        for case in cases {
+            let name = case_name(case);
+            let arguments = if maps {
+                "self".to_owned()
+            } else {
+                case.parameters
+                    .get_all_params()
+                    .iter()
+                    .map(|e| format!("self.{}", e.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let call = if required {
+                format!("return {name}({arguments});")
+            } else if or_else {
+                format!("if ({name} != null) return {name}({arguments});")
+            } else {
+                format!("return {name}?.call({arguments});")
+            };
+            let _ = writeln!(output, "    if (self is {}) {{", case_type(case));
+            let _ = writeln!(output, "      {call}");
+            let _ = writeln!(output, "    }}");
+        }
+
+        if or_else {
+            let _ = writeln!(output, "    return orElse();");
+        } else {
+            let _ = writeln!(
+                output,
+                "    throw StateError('Unexpected subclass ${{self.runtimeType}}');"
+            );
+        }
+        let _ = writeln!(output, "  }}");
+        let _ = writeln!(output);
+    }
+}
+
+/// Re-emits `///` comments from the source, so IDE hovers keep working on generated members
+pub fn generate_documentation(output: &mut String, documentation: &Option<String>, indent: &str) {
+    let Some(documentation) = documentation else {
+        return;
+    };
+
+    for line in documentation.lines() {
+        let _ = writeln!(output, "{indent}{}", line.trim());
     }
 }
 
@@ -102,7 +249,12 @@ pub fn generate_mixin_getset_functions(
     }
 }
 
-pub fn generate_eq_operator(output: &mut String, mixin_type: &str, fields: &[PositionalParameter]) {
+pub fn generate_eq_operator(
+    output: &mut String,
+    context: &GenerationContext,
+    mixin_type: &str,
+    fields: &[PositionalParameter],
+) {
     let _ = writeln!(
         output,
         r#"  @override
@@ -114,7 +266,7 @@ pub fn generate_eq_operator(output: &mut String, mixin_type: &str, fields: &[Pos
 
     for field in fields {
         let _ = write!(output, "         && ");
-        generate_comparator(output, &field.name, &field.dart_type);
+        generate_comparator(output, context, &field.name, &field.dart_type);
         let _ = writeln!(output);
     }
 
@@ -125,12 +277,20 @@ pub fn generate_eq_operator(output: &mut String, mixin_type: &str, fields: &[Pos
     );
 }
 
-pub fn generate_comparator(output: &mut String, field_name: &str, dart_type: &DartType) {
-    if dart_type.is_collection() {
+pub fn generate_comparator(
+    output: &mut String,
+    context: &GenerationContext,
+    field_name: &str,
+    dart_type: &DartType,
+) {
+    if context.runtime_type(dart_type).is_collection() {
         let _ = write!(
             output,
             "const DeepCollectionEquality().equals(other.{field_name}, {field_name})"
         );
+    } else if context.extension_type(dart_type).is_some() {
+        // Extension types are their representation at runtime, so compare by value
+        let _ = write!(output, "other.{field_name} == {field_name}");
     } else {
         // Simple cmp
         let _ = write!(
@@ -141,7 +301,11 @@ pub fn generate_comparator(output: &mut String, field_name: &str, dart_type: &Da
     }
 }
 
-pub fn generate_hash_operator(output: &mut String, fields: &[PositionalParameter]) {
+pub fn generate_hash_operator(
+    output: &mut String,
+    context: &GenerationContext,
+    fields: &[PositionalParameter],
+) {
     if fields.is_empty() {
         let _ = writeln!(
             output,
@@ -169,7 +333,7 @@ pub fn generate_hash_operator(output: &mut String, fields: &[PositionalParameter
 
     for field in fields {
         let _ = write!(output, "    ");
-        generate_hash_line(output, &field.name, &field.dart_type);
+        generate_hash_line(output, context, &field.name, &field.dart_type);
         let _ = writeln!(output, ",");
     }
 
@@ -180,8 +344,13 @@ pub fn generate_hash_operator(output: &mut String, fields: &[PositionalParameter
     }
 }
 
-pub fn generate_hash_line(output: &mut String, field_name: &str, dart_type: &DartType) {
-    if dart_type.is_collection() {
+pub fn generate_hash_line(
+    output: &mut String,
+    context: &GenerationContext,
+    field_name: &str,
+    dart_type: &DartType,
+) {
+    if context.runtime_type(dart_type).is_collection() {
         let _ = write!(output, "const DeepCollectionEquality().hash({field_name})");
     } else {
         // Simple cmp
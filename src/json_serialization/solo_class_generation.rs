@@ -1,11 +1,12 @@
 use crate::dart_types::{
-    DartType, NamedParameter, ParameterList, RedirectedConstructor, get_generic_string,
+    Annotation, DartType, NamedParameter, ParameterList, PositionalParameter,
+    RedirectedConstructor, get_generic_string,
 };
 use std::fmt::Write;
 
 use super::{
-    generate_eq_operator, generate_hash_operator, generate_mixin_copywith_function,
-    generate_to_string, to_json_method_generator,
+    GenerationContext, generate_documentation, generate_eq_operator, generate_hash_operator,
+    generate_mixin_copywith_function, generate_to_string, to_json_method_generator,
 };
 
 pub enum JsonMethod {
@@ -18,6 +19,7 @@ pub enum JsonMethod {
 #[allow(clippy::too_many_arguments)]
 pub fn generate_solo_class(
     output: &mut String,
+    context: &GenerationContext,
     class_name: &str,
     class_generics: &[DartType],
     class_to_json: JsonMethod,
@@ -25,6 +27,9 @@ pub fn generate_solo_class(
     parameters: &ParameterList,
     is_const: bool,
     unnamed_constructor: &Option<RedirectedConstructor>,
+    mixins: &[DartType],
+    interfaces: &[DartType],
+    mixin_fields: &[PositionalParameter],
 ) {
     let mut copywith_generics = class_generics.to_owned();
     copywith_generics.push(DartType {
@@ -35,15 +40,24 @@ pub fn generate_solo_class(
     let just_generics = get_generic_string(class_generics);
     let redirected_type = format!("{redirected_name}{just_generics}");
 
-    let superclass = if unnamed_constructor.is_some() {
-        format!("extends {class_name}{just_generics}")
+    let mut superclass = String::new();
+    let mut implemented = interfaces.iter().map(|e| e.as_raw()).collect::<Vec<_>>();
+    if unnamed_constructor.is_some() {
+        let _ = write!(superclass, " extends {class_name}{just_generics}");
     } else {
-        format!("implements {class_name}{just_generics}")
-    };
+        implemented.insert(0, format!("{class_name}{just_generics}"));
+    }
+    if !mixins.is_empty() {
+        let mixins = mixins.iter().map(|e| e.as_raw()).collect::<Vec<_>>();
+        let _ = write!(superclass, " with {}", mixins.join(", "));
+    }
+    if !implemented.is_empty() {
+        let _ = write!(superclass, " implements {}", implemented.join(", "));
+    }
 
     let _ = writeln!(
         output,
-        "class {redirected_name}{just_generics} {superclass} {{"
+        "class {redirected_name}{just_generics}{superclass} {{"
     );
     if is_const {
         let _ = writeln!(output, "const");
@@ -52,8 +66,12 @@ pub fn generate_solo_class(
 
     for pos_field in &parameters.positional_parameters {
         let mut default_value: String = String::new();
-        if let Some(default) = pos_field.annotations.iter().find(|e| e.name == "Default") {
-            default_value = format!(" = {}", default.get_default_value());
+        if let Some(default) = pos_field
+            .annotations
+            .iter()
+            .find_map(Annotation::get_default_value)
+        {
+            default_value = format!(" = {default}");
         }
 
         let _ = write!(output, "this.{}, ", pos_field.name);
@@ -74,9 +92,17 @@ pub fn generate_solo_class(
         let _ = writeln!(output, ");");
     }
 
-    // Here @override delection
     for parameter in parameters.get_all_params() {
-        let _ = write!(
+        generate_documentation(output, &parameter.documentation, "  ");
+        for annotation in &parameter.annotations {
+            if !annotation.is_freezed_internal() {
+                let _ = writeln!(output, "  {}", annotation.source);
+            }
+        }
+        if mixin_fields.iter().any(|e| e.name == parameter.name) {
+            let _ = writeln!(output, "  @override");
+        }
+        let _ = writeln!(
             output,
             "  final {} {};",
             parameter.dart_type.as_raw(),
@@ -84,9 +110,14 @@ pub fn generate_solo_class(
         );
     }
 
-    generate_eq_operator(output, &redirected_type, &parameters.get_all_params());
+    generate_eq_operator(
+        output,
+        context,
+        &redirected_type,
+        &parameters.get_all_params(),
+    );
     let _ = writeln!(output);
-    generate_hash_operator(output, &parameters.get_all_params());
+    generate_hash_operator(output, context, &parameters.get_all_params());
     let _ = writeln!(output);
     generate_to_string(output, class_name, &parameters.get_all_params(), true);
     let _ = writeln!(output);
@@ -136,8 +167,12 @@ fn generate_named_parameter(output: &mut String, parameter: &NamedParameter) {
     let _ = write!(output, "this.{}", parameter.name);
 
     let mut default_value: String = String::new();
-    if let Some(default) = parameter.annotations.iter().find(|e| e.name == "Default") {
-        default_value = format!(" = {}", default.get_default_value());
+    if let Some(default) = parameter
+        .annotations
+        .iter()
+        .find_map(Annotation::get_default_value)
+    {
+        default_value = format!(" = {default}");
     }
     let _ = write!(output, "{default_value}");
 }
@@ -1,8 +1,19 @@
+pub mod build_config;
 pub mod dart_types;
+pub mod diagnostics;
 pub mod json_serialization;
+pub mod package_config;
 pub mod parser;
+pub mod project_files;
+pub mod project_index;
+
+use std::fmt::Write;
 
 use dart_types::ClassDefinition;
+use diagnostics::Diagnostic;
+use json_serialization::{
+    FreezedVersion, GenerationContext, PRIVATE_CONSTRUCTOR_ERROR, generate_class,
+};
 
 /// Parse Dart code and extract all classes with @freezed annotation
 ///
@@ -10,7 +21,8 @@ use dart_types::ClassDefinition;
 /// * `code` - The Dart source code as a string
 ///
 /// # Returns
-/// * A vector of FreezedClass instances representing the parsed classes
+/// * A vector of FreezedClass instances representing the parsed classes, classes that
+///   couldn't be parsed are skipped, use `parser::parse_dart_code` to get the diagnostics
 ///
 /// # Example
 /// ```
@@ -31,4 +43,118 @@ use dart_types::ClassDefinition;
 /// ```
 pub fn parse_freezed_classes(code: String) -> Vec<ClassDefinition> {
     parser::parse_dart_code(&code)
+        .classes
+        .into_iter()
+        .filter(ClassDefinition::is_freezed)
+        .collect()
+}
+
+/// What becomes of a generated file already on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneratedFile {
+    Write(String),
+    /// The source doesn't ask for it anymore
+    Remove,
+    /// Left as it was, the source couldn't be generated completely
+    Keep,
+}
+
+impl GeneratedFile {
+    pub fn is_empty(&self) -> bool {
+        !matches!(self, GeneratedFile::Write(_))
+    }
+}
+
+/// The `.freezed.dart` and `.g.dart` of a source file
+#[derive(Debug, Clone)]
+pub struct GeneratedFiles {
+    pub freezed: GeneratedFile,
+    pub json: GeneratedFile,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Classes that were parsed and generated
+    pub class_count: usize,
+    /// Syntax errors, the file is mid-edit and nothing was generated
+    pub is_skipped: bool,
+}
+
+/// Generates the parts of `code`, the library file called `file_name`, which are written
+/// to the `generated/` directory next to it.
+///
+/// A previously generated file is only removed when the source no longer needs it. After
+/// an error the last good one is kept, as its `part` directive would break otherwise.
+pub fn generate_files(code: &str, file_name: &str, context: &GenerationContext) -> GeneratedFiles {
+    let result = parser::parse_dart_code(code);
+    let has_errors = result.has_errors();
+    let mut generated = GeneratedFiles {
+        freezed: GeneratedFile::Keep,
+        json: GeneratedFile::Keep,
+        class_count: result.classes.len(),
+        is_skipped: result.has_syntax_errors(),
+        diagnostics: result.diagnostics,
+    };
+    if context.options.freezed_version == FreezedVersion::V3 {
+        generated
+            .diagnostics
+            .extend(result.classes.iter().filter_map(concrete_class_warning));
+    }
+    if generated.is_skipped || result.classes.is_empty() {
+        return generated;
+    }
+
+    let part_of = format!("part of '../{file_name}';");
+
+    let mut freezed_file = String::new();
+    let _ = write!(&mut freezed_file, "// dart format width=80
+// coverage:ignore-file
+// GENERATED CODE - DO NOT MODIFY BY HAND
+// ignore_for_file: type=lint, unnecessary_cast
+// ignore_for_file: unused_element, deprecated_member_use, deprecated_member_use_from_same_package, use_function_type_syntax_for_parameters, unnecessary_const, avoid_init_to_null, invalid_override_different_default_values_named, prefer_expression_function_bodies, annotate_overrides, invalid_annotation_target, unnecessary_question_mark
+");
+    freezed_file.push_str(&part_of);
+    freezed_file.push_str("\nT _$identity<T>(T value) => value;\n");
+    if context.options.freezed_version == FreezedVersion::V2 {
+        freezed_file.push_str(PRIVATE_CONSTRUCTOR_ERROR);
+    }
+    let mut g_file = part_of;
+
+    let init_freezed_len = freezed_file.len();
+    let init_g_len = g_file.len();
+    for class in &result.classes {
+        generate_class(&mut freezed_file, &mut g_file, class, context);
+    }
+
+    // A freezed class that failed still needs the file its `part` points to
+    let has_freezed_source = ["@freezed", "@Freezed"].iter().any(|e| code.contains(e));
+    generated.freezed = if freezed_file.len() > init_freezed_len {
+        GeneratedFile::Write(freezed_file)
+    } else if has_errors || has_freezed_source {
+        GeneratedFile::Keep
+    } else {
+        GeneratedFile::Remove
+    };
+    generated.json = if g_file.len() > init_g_len {
+        GeneratedFile::Write(g_file)
+    } else if has_errors {
+        GeneratedFile::Keep
+    } else {
+        GeneratedFile::Remove
+    };
+
+    generated
+}
+
+/// freezed 3 only implements factory constructors of `abstract` or `sealed` classes, the
+/// generated code doesn't compile against a concrete one
+fn concrete_class_warning(class: &ClassDefinition) -> Option<Diagnostic> {
+    if !class.is_freezed() || class.is_abstract || class.redirecting_constructors.is_empty() {
+        return None;
+    }
+    Some(Diagnostic::warning(
+        "concrete-freezed-class",
+        format!(
+            "freezed 3 needs `{}` to be `abstract` or `sealed`, or `--freezed-version=2`",
+            class.name
+        ),
+        class.span,
+    ))
 }
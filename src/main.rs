@@ -1,6 +1,4 @@
 use std::env;
-use std::fmt::Write;
-use std::fs;
 use std::fs::read_to_string;
 use std::io::ErrorKind;
 use std::path::Path;
@@ -8,98 +6,121 @@ use std::path::PathBuf;
 use std::process::ExitCode;
 use std::time::Instant;
 
-use faster_freezed::json_serialization::generate_class;
-use faster_freezed::parser::parse_dart_code;
-
-fn traverse_directory(path: &Path) -> Vec<PathBuf> {
-    let Ok(directory) = fs::read_dir(path) else {
-        eprintln!("[E] Error reading directory {path:?}");
-        return Vec::new();
-    };
-
-    let mut files = Vec::new();
+use faster_freezed::build_config::BuildConfig;
+use faster_freezed::diagnostics::Diagnostic;
+use faster_freezed::json_serialization::{FreezedVersion, GenerationContext, GeneratorOptions};
+use faster_freezed::package_config::{PackageConfig, package_root};
+use faster_freezed::project_files::{ProjectFiles, TraversalOptions};
+use faster_freezed::project_index::ProjectIndex;
+use faster_freezed::{GeneratedFile, generate_files};
+
+/// A directory with a `pubspec.yaml`, its files generated with its own `build.yaml` and
+/// `package_config.json`
+struct Package {
+    root: Option<PathBuf>,
+    config: PackageConfig,
+    options: GeneratorOptions,
+}
 
-    for entry in directory {
-        let Ok(entry) = entry else {
-            eprintln!("[E] Error reading directory {path:?}");
-            continue;
-        };
+/// What the command line asks for, over every package's own configuration
+struct Overrides {
+    checked: bool,
+    freezed_version: Option<FreezedVersion>,
+}
 
-        if entry.path().is_file() {
-            let path = entry.path();
-            let name = path.file_name().unwrap().to_str().unwrap();
-            if name.ends_with(".dart")
-                && !name.ends_with(".g.dart")
-                && !name.ends_with(".freezed.dart")
-            {
-                files.push(entry.path());
+impl Package {
+    fn load(root: Option<PathBuf>, directory: &Path, overrides: &Overrides) -> Self {
+        let mut options = GeneratorOptions::default();
+        if let Some(root) = &root {
+            let build_config = BuildConfig::load(root, &options);
+            for warning in &build_config.warnings {
+                eprintln!("[W] {warning}");
             }
+            options = build_config.options;
+        }
+        // The command line overrides build.yaml and pubspec.lock
+        options.json.checked |= overrides.checked;
+        if let Some(freezed_version) = overrides.freezed_version {
+            options.freezed_version = freezed_version;
         }
 
-        if entry.path().is_dir() {
-            files.extend(traverse_directory(&entry.path()));
+        Self {
+            config: PackageConfig::discover(root.as_deref().unwrap_or(directory)),
+            root,
+            options,
         }
     }
-
-    files
 }
 
-fn process_file(data: &str, path: &Path) {
-    let classes = parse_dart_code(data);
-    if classes.is_empty() {
-        eprintln!("Found '@freezed' string in {path:?} but couldn't parse it",);
-        return;
+/// Returns whether any error was reported for the file
+fn process_file(
+    data: &str,
+    path: &Path,
+    index: &ProjectIndex,
+    config: &PackageConfig,
+    options: &GeneratorOptions,
+) -> bool {
+    let scope = index.scope_for(path, config);
+    let context = GenerationContext::new(index, &scope, config, options);
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    let generated = generate_files(data, file_name, &context);
+
+    for diagnostic in &generated.diagnostics {
+        eprintln!("{}:{diagnostic}", path.display());
     }
+    let has_errors = generated.diagnostics.iter().any(Diagnostic::is_error);
 
-    let part_of = format!(
-        "part of '../{}';",
-        path.file_name().unwrap().to_str().unwrap()
-    );
-
-    let identity = "\nT _$identity<T>(T value) => value;\n";
-
-    let mut freezed_file = String::new();
-    let _ = write!(&mut freezed_file, "// dart format width=80
-// coverage:ignore-file
-// GENERATED CODE - DO NOT MODIFY BY HAND
-// ignore_for_file: type=lint, unnecessary_cast
-// ignore_for_file: unused_element, deprecated_member_use, deprecated_member_use_from_same_package, use_function_type_syntax_for_parameters, unnecessary_const, avoid_init_to_null, invalid_override_different_default_values_named, prefer_expression_function_bodies, annotate_overrides, invalid_annotation_target, unnecessary_question_mark
-");
-    freezed_file.push_str(&part_of);
-    freezed_file.push_str(identity);
-
-    let mut g_file = part_of.clone();
-
-    let init_g_len = g_file.len();
-    let init_freezed_len = freezed_file.len();
-
-    for class in classes {
-        generate_class(&mut freezed_file, &mut g_file, &class);
+    if generated.is_skipped {
+        eprintln!("Skipping {path:?}, keeping its previously generated files");
+        return has_errors;
+    }
+    if generated.class_count == 0 {
+        if generated.diagnostics.is_empty() {
+            eprintln!(
+                "Found a '@freezed' or '@JsonSerializable' string in {path:?} but couldn't parse it"
+            );
+        }
+        return has_errors;
+    }
+    if !has_errors && generated.freezed.is_empty() && generated.json.is_empty() {
+        eprintln!("[E] Nothing was generated for {path:?}, while there's a parsed class.");
+        return true;
     }
 
     let mut parent_dir = path.parent().unwrap().to_owned();
-    let file_name = path.file_stem().unwrap().to_str().unwrap();
-    let freezed_file_name = format!("{file_name}.freezed.dart");
-    let json_file_name = format!("{file_name}.g.dart");
+    let file_stem = path.file_stem().unwrap().to_str().unwrap();
 
     parent_dir.push("generated");
-    let r = std::fs::create_dir(&parent_dir);
-    assert!(r.is_ok() || r.is_err_and(|e| e.kind() == ErrorKind::AlreadyExists));
-
-    let freezed_file_path = parent_dir.join(freezed_file_name);
-    let g_file_path = parent_dir.join(json_file_name);
-
-    if freezed_file.len() > init_freezed_len {
-        std::fs::write(freezed_file_path, freezed_file).unwrap();
-    } else {
-        panic!("Tried to write empty file, while there's a parsed class.");
+    if let Err(e) = std::fs::create_dir(&parent_dir)
+        && e.kind() != ErrorKind::AlreadyExists
+    {
+        eprintln!("[E] Error creating directory {parent_dir:?}: {e}");
+        return true;
     }
 
-    if g_file.len() > init_g_len {
-        std::fs::write(g_file_path, g_file).unwrap();
-    } else if g_file_path.is_file() {
-        std::fs::remove_file(g_file_path).unwrap();
+    let outputs = [
+        (
+            parent_dir.join(format!("{file_stem}.freezed.dart")),
+            generated.freezed,
+        ),
+        (
+            parent_dir.join(format!("{file_stem}.g.dart")),
+            generated.json,
+        ),
+    ];
+    for (output_path, output) in outputs {
+        let result = match output {
+            GeneratedFile::Write(content) => std::fs::write(&output_path, content),
+            GeneratedFile::Remove if output_path.is_file() => std::fs::remove_file(&output_path),
+            GeneratedFile::Remove | GeneratedFile::Keep => Ok(()),
+        };
+        if let Err(e) = result {
+            eprintln!("[E] Error writing {output_path:?}: {e}");
+            return true;
+        }
     }
+
+    has_errors
 }
 
 fn main() -> ExitCode {
@@ -107,40 +128,107 @@ fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
     assert!(!args.is_empty());
 
-    if args.len() != 2 {
+    let mut overrides = Overrides {
+        checked: false,
+        freezed_version: None,
+    };
+    let mut traversal = TraversalOptions::default();
+    let mut targets = Vec::new();
+    for arg in &args[1..] {
+        if let Some(name) = arg.strip_prefix("--skip-dir=") {
+            traversal.skipped_directories.push(name.to_owned());
+            continue;
+        }
+        if let Some(name) = arg.strip_prefix("--include-dir=") {
+            traversal.skipped_directories.retain(|e| e != name);
+            continue;
+        }
+        match arg.as_str() {
+            "--checked" => overrides.checked = true,
+            "--include-examples" => traversal.include_examples = true,
+            "--freezed-version=2" => overrides.freezed_version = Some(FreezedVersion::V2),
+            "--freezed-version=3" => overrides.freezed_version = Some(FreezedVersion::V3),
+            _ if arg.starts_with("--") => {
+                println!("Unknown option {arg}");
+                targets.clear();
+                break;
+            }
+            _ => targets.push(arg),
+        }
+    }
+
+    if targets.len() != 1 {
         println!("Invalid usage.");
-        println!("    Usage: faster_freezed <TARGET_DIRECTORY>");
-        return ExitCode::SUCCESS;
+        println!(
+            "    Usage: faster_freezed [--checked] [--include-examples] [--skip-dir=<NAME>] [--include-dir=<NAME>] [--freezed-version=2|3] <TARGET_DIRECTORY>"
+        );
+        println!(
+            "    Package roots skip build/ and .dart_tool/, --include-dir=build generates in build/ as well"
+        );
+        return ExitCode::FAILURE;
     }
 
-    let path = Path::new(&args[1]);
-    let dart_files = traverse_directory(path);
+    let path = Path::new(targets[0]);
+    // The target may be a package, a directory inside one, or a monorepo holding several
+    let top_package_root = package_root(path);
+    let ProjectFiles {
+        files: dart_files,
+        package_roots,
+    } = ProjectFiles::collect(path, &traversal);
     let dart_files_count = dart_files.len();
     let traversing_timer = start.elapsed();
 
     let mut files_to_process = Vec::new();
 
-    for file in dart_files {
-        let x = read_to_string(&file).unwrap();
-        if x.contains("@freezed") {
-            files_to_process.push((file, x));
+    let mut failed_files = 0;
+    // Every file is indexed, the types used by freezed classes are declared anywhere
+    let mut index = ProjectIndex::new();
+
+    for (file, package) in dart_files {
+        let x = match read_to_string(&file) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("[E] Error reading {file:?}: {e}");
+                failed_files += 1;
+                continue;
+            }
+        };
+        index.index_file(&file, &x);
+        if ["@freezed", "@Freezed", "@JsonSerializable"]
+            .iter()
+            .any(|e| x.contains(e))
+        {
+            files_to_process.push((file, package, x));
         }
     }
     let force_search_timing = start.elapsed();
 
-    for file in &files_to_process {
-        //println!("Processing {:?}", file.0);
-        process_file(&file.1, &file.0);
+    let top_package = Package::load(top_package_root, path, &overrides);
+    let packages: Vec<Package> = package_roots
+        .into_iter()
+        .map(|root| Package::load(Some(root), path, &overrides))
+        .collect();
+
+    for (file, package, data) in &files_to_process {
+        let package = package.map_or(&top_package, |e| &packages[e]);
+        if process_file(data, file, &index, &package.config, &package.options) {
+            failed_files += 1;
+        }
     }
 
     let parsing_and_generating = start.elapsed();
 
-    println!("Took {traversing_timer:?} to discover file tree");
     println!(
-        "Found {} freezed files from {} dart files in {:?}",
+        "Took {traversing_timer:?} to discover file tree, {} package(s)",
+        packages.len() + usize::from(top_package.root.is_some())
+    );
+    println!(
+        "Found {} freezed or json_serializable files from {} dart files in {:?}, indexed {} types from {} parsed files",
         files_to_process.len(),
         dart_files_count,
-        force_search_timing - traversing_timer
+        force_search_timing - traversing_timer,
+        index.len(),
+        index.parsed_files()
     );
 
     println!(
@@ -149,5 +237,10 @@ fn main() -> ExitCode {
     );
     println!("Total: {:?}", start.elapsed());
 
+    if failed_files > 0 {
+        eprintln!("[E] {failed_files} file(s) had errors");
+        return ExitCode::FAILURE;
+    }
+
     ExitCode::SUCCESS
 }
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::Value;
+
+/// Where `package:` URIs point to, read from `.dart_tool/package_config.json`
+#[derive(Debug, Default, Clone)]
+pub struct PackageConfig {
+    /// Package name to the directory its `package:name/` URIs are relative to
+    packages: HashMap<String, PathBuf>,
+}
+
+impl PackageConfig {
+    /// Looks for `.dart_tool/package_config.json` in `directory` and its parents. Without
+    /// one (`pub get` wasn't run), only the package of the closest `pubspec.yaml` is known.
+    pub fn discover(directory: &Path) -> Self {
+        let directory = normalize_path(directory);
+
+        for ancestor in directory.ancestors() {
+            let config_path = ancestor.join(".dart_tool").join("package_config.json");
+            if let Ok(content) = fs::read_to_string(&config_path) {
+                match Self::parse(&content, &config_path) {
+                    Some(config) => return config,
+                    None => eprintln!("[E] Couldn't parse {config_path:?}, ignoring it"),
+                }
+            }
+        }
+
+        let mut config = Self::default();
+        if let Some(root) = package_root(&directory)
+            && let Ok(pubspec) = fs::read_to_string(root.join("pubspec.yaml"))
+            && let Some(name) = pubspec_name(&pubspec)
+        {
+            config.packages.insert(name, root.join("lib"));
+        }
+        config
+    }
+
+    /// Parses the content of `config_path`, relative URIs are resolved against it
+    pub fn parse(content: &str, config_path: &Path) -> Option<Self> {
+        let json: Value = serde_json::from_str(content).ok()?;
+        let config_directory = config_path.parent().unwrap_or(Path::new(""));
+
+        let mut packages = HashMap::new();
+        for package in json.get("packages")?.as_array()? {
+            let (Some(name), Some(root_uri)) = (
+                package.get("name").and_then(Value::as_str),
+                package.get("rootUri").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            let package_uri = package
+                .get("packageUri")
+                .and_then(Value::as_str)
+                .unwrap_or("");
+
+            let root = match root_uri.strip_prefix("file://") {
+                Some(absolute) => PathBuf::from(percent_decode(absolute)),
+                None => config_directory.join(percent_decode(root_uri)),
+            };
+            packages.insert(
+                name.to_owned(),
+                normalize_path(&root.join(percent_decode(package_uri))),
+            );
+        }
+
+        Some(Self { packages })
+    }
+
+    pub fn package_directory(&self, name: &str) -> Option<&Path> {
+        self.packages.get(name).map(PathBuf::as_path)
+    }
+
+    /// The file an `import`, `export` or `part` URI in `from` refers to. `None` for `dart:`
+    /// libraries and packages missing from the config.
+    pub fn resolve_uri(&self, from: &Path, uri: &str) -> Option<PathBuf> {
+        if uri.starts_with("dart:") {
+            return None;
+        }
+
+        if let Some(package_path) = uri.strip_prefix("package:") {
+            let (package, path) = package_path.split_once('/')?;
+            return Some(normalize_path(&self.package_directory(package)?.join(path)));
+        }
+
+        if let Some(absolute) = uri.strip_prefix("file://") {
+            return Some(normalize_path(Path::new(absolute)));
+        }
+
+        let directory = from.parent().unwrap_or(Path::new(""));
+        Some(normalize_path(&directory.join(percent_decode(uri))))
+    }
+}
+
+/// Absolute path with `.` and `..` removed, without touching the file system so it works
+/// for files that don't exist and keeps symlinks as they were given
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The closest directory with a `pubspec.yaml`, `directory` included
+pub fn package_root(directory: &Path) -> Option<PathBuf> {
+    normalize_path(directory)
+        .ancestors()
+        .find(|e| e.join("pubspec.yaml").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The `name:` of a pubspec
+pub fn pubspec_name(pubspec: &str) -> Option<String> {
+    pubspec.lines().find_map(|line| {
+        let name = line.strip_prefix("name:")?;
+        let name = name.split('#').next()?.trim().trim_matches(['\'', '"']);
+        (!name.is_empty()).then(|| name.to_owned())
+    })
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = text.get(i + 1..i + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
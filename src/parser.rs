@@ -1,14 +1,18 @@
 use crate::dart_types::{
-    Annotation, ClassDefinition, DartType, NamedParameter, ParameterList, PositionalParameter,
-    RedirectedConstructor,
+    Annotation, ClassDefinition, DartType, NamedParameter, ParameterList, Position,
+    PositionalParameter, RedirectedConstructor, Span,
 };
+use crate::diagnostics::Diagnostic;
+use crate::project_index::extension_type_ranges;
 use lazy_static::lazy_static;
+use std::ops::Range;
 use streaming_iterator::StreamingIterator;
 use tree_sitter::{Parser, QueryCursor};
 
-const FREEZED_CLASS: &str = "(class_definition (annotation) @freezed_marker) @class_definition";
-const CLASS_MEMBER_DEFINITION: &str =
-    "((declaration) @member)\n((factory_constructor_signature) @member)";
+const CLASS_DEFINITION: &str = "(class_definition) @class_definition";
+const CLASS_MEMBER_DEFINITION: &str = "((declaration) @member)
+((factory_constructor_signature) @member)
+((method_signature (constructor_signature) @member))";
 
 unsafe extern "C" {
     pub fn tree_sitter_dart() -> tree_sitter::Language;
@@ -16,8 +20,8 @@ unsafe extern "C" {
 
 lazy_static! {
     pub static ref DART_TS: tree_sitter::Language = unsafe { tree_sitter_dart() };
-    static ref freezed_class_q: tree_sitter::Query =
-        tree_sitter::Query::new(&DART_TS, FREEZED_CLASS).expect("hardcoded");
+    static ref class_definition_q: tree_sitter::Query =
+        tree_sitter::Query::new(&DART_TS, CLASS_DEFINITION).expect("hardcoded");
     static ref members_q: tree_sitter::Query =
         tree_sitter::Query::new(&DART_TS, CLASS_MEMBER_DEFINITION).expect("hardcoded");
 }
@@ -26,8 +30,113 @@ pub fn get_text(node: tree_sitter::Node, code: &str) -> String {
     node.utf8_text(code.as_bytes()).unwrap().to_owned()
 }
 
+/// Output of parsing one file, classes that failed to parse are left out and
+/// explained in `diagnostics`
+#[derive(Debug, Default)]
+pub struct ParseResult {
+    pub classes: Vec<ClassDefinition>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    /// The file is mid-edit, its previous output should be kept as is
+    pub fn has_syntax_errors(&self) -> bool {
+        self.diagnostics.iter().any(|e| e.code == SYNTAX_ERROR)
+    }
+}
+
+pub const SYNTAX_ERROR: &str = "syntax-error";
+
+/// Reports the outermost `ERROR` and every `MISSING` node below `node`, except within
+/// `ignored`, the extension types the grammar doesn't know about
+fn collect_syntax_errors(
+    node: tree_sitter::Node,
+    code: &str,
+    context: &str,
+    ignored: &[Range<usize>],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let span = get_span(node);
+    if node.is_error() || node.is_missing() {
+        let text = &code[node.start_byte()..node.end_byte()];
+        let start = node.start_byte() + text.len() - text.trim_start().len();
+        let end = node.end_byte() - (text.len() - text.trim_end().len());
+        if ignored.iter().any(|e| e.start <= start && end <= e.end) {
+            return;
+        }
+
+        let problem = if node.is_missing() {
+            format!("missing `{}`", node.kind())
+        } else {
+            let text = get_text(node, code);
+            let text = text.lines().next().unwrap_or_default();
+            format!("unexpected `{}`", text.chars().take(40).collect::<String>())
+        };
+
+        diagnostics.push(Diagnostic::error(
+            SYNTAX_ERROR,
+            format!("{problem} in {context}{}", source_snippet(code, span)),
+            span,
+        ));
+        return;
+    }
+
+    if !node.has_error() {
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_syntax_errors(child, code, context, ignored, diagnostics);
+    }
+}
+
+/// The source line of `span` with a caret under its start
+fn source_snippet(code: &str, span: Span) -> String {
+    let line = code.lines().nth(span.start.line - 1).unwrap_or_default();
+    let gutter = " ".repeat(span.start.line.to_string().len());
+
+    format!(
+        "\n{gutter} |\n{} | {line}\n{gutter} | {}^",
+        span.start.line,
+        " ".repeat(span.start.column - 1)
+    )
+}
+
+fn unexpected_node(node: tree_sitter::Node, code: &str, expected: &str) -> Diagnostic {
+    let text = get_text(node, code);
+    let mut snippet = text.lines().next().unwrap_or_default().to_string();
+    if snippet.len() > 40 || text.contains('\n') {
+        snippet = format!("{}...", snippet.chars().take(40).collect::<String>());
+    }
+
+    Diagnostic::error(
+        "unexpected-syntax",
+        format!("expected {expected}, found `{snippet}` ({})", node.kind()),
+        get_span(node),
+    )
+}
+
+pub fn get_span(node: tree_sitter::Node) -> Span {
+    let position = |point: tree_sitter::Point| Position {
+        line: point.row + 1,
+        column: point.column + 1,
+    };
+
+    Span {
+        start_byte: node.start_byte(),
+        end_byte: node.end_byte(),
+        start: position(node.start_position()),
+        end: position(node.end_position()),
+    }
+}
+
 /// Parse Dart code and extract all classes with @freezed annotation
-pub fn parse_dart_code(code: &str) -> Vec<ClassDefinition> {
+pub fn parse_dart_code(code: &str) -> ParseResult {
     let mut parser = Parser::new();
     parser
         .set_language(&DART_TS)
@@ -35,97 +144,218 @@ pub fn parse_dart_code(code: &str) -> Vec<ClassDefinition> {
     let tree = parser.parse(code, None).unwrap();
 
     let mut query_cursor = QueryCursor::new();
-    let mut output_freezed_classes = Vec::new();
-
-    let mut class_with_annotation_matches =
-        query_cursor.matches(&freezed_class_q, tree.root_node(), code.as_bytes());
-
-    while let Some(class_with_annotation) = class_with_annotation_matches.next() {
-        let _freezed_annotation = class_with_annotation
-            .nodes_for_capture_index(0)
-            .next()
-            .unwrap();
-
-        let mut gen_form = false;
-        if let Some(element) = _freezed_annotation.next_sibling()
-            && element
-                .utf8_text(code.as_bytes())
-                .unwrap()
-                .contains("@qform")
+    let mut result = ParseResult::default();
+
+    let mut class_matches =
+        query_cursor.matches(&class_definition_q, tree.root_node(), code.as_bytes());
+
+    let extension_types = extension_type_ranges(code);
+
+    // A freezed class broken badly enough doesn't even parse as a class
+    let root = tree.root_node();
+    let mut root_cursor = root.walk();
+    for child in root.children(&mut root_cursor) {
+        if child.has_error()
+            && child.kind() != "class_definition"
+            && ["@freezed", "@Freezed", "@JsonSerializable"]
+                .iter()
+                .any(|e| get_text(child, code).contains(e))
         {
-            gen_form = true;
+            collect_syntax_errors(
+                child,
+                code,
+                "a generated declaration",
+                &extension_types,
+                &mut result.diagnostics,
+            );
         }
+    }
 
-        if get_text(_freezed_annotation, code) != "@freezed" {
+    while let Some(class_match) = class_matches.next() {
+        let class_declaration = class_match.nodes_for_capture_index(0).next().unwrap();
+
+        let annotations = parse_class_annotations(class_declaration, code);
+        // Plain `@JsonSerializable` classes only get the `.g.dart` functions
+        let kind = if annotations.iter().any(Annotation::is_freezed) {
+            "freezed class"
+        } else if annotations.iter().any(|e| e.name == "JsonSerializable") {
+            "json_serializable class"
+        } else {
             continue;
+        };
+
+        if class_declaration.has_error() {
+            let class_name = class_declaration
+                .child_by_field_name("name")
+                .map(|e| format!("{kind} `{}`", get_text(e, code)))
+                .unwrap_or_else(|| kind.to_string());
+            let mut errors = Vec::new();
+            collect_syntax_errors(
+                class_declaration,
+                code,
+                &class_name,
+                &extension_types,
+                &mut errors,
+            );
+            if !errors.is_empty() {
+                result.diagnostics.extend(errors);
+                continue;
+            }
         }
 
-        // We can get multiple body matches, but there's only one body
-        let class_declaration = class_with_annotation
-            .nodes_for_capture_index(1)
-            .next()
-            .unwrap();
-
-        let class_name = class_declaration
-            .child_by_field_name("name")
-            .unwrap()
-            .utf8_text(code.as_bytes())
-            .unwrap();
-
-        // FEAT: support multiple mixins
-        let mixin = 'mixin: {
-            let superclass = class_declaration.child_by_field_name("superclass").unwrap();
-            let mut superclass_cursor = superclass.walk();
-            for child in superclass.children(&mut superclass_cursor) {
-                if child.kind() == "mixins" {
-                    let (dart_type, _skip) = parse_type(child.named_child(0).unwrap(), code);
-                    break 'mixin Some(dart_type);
-                }
+        // Only the offending class is skipped, the rest of the file is still generated
+        match parse_class(
+            class_declaration,
+            annotations,
+            code,
+            &mut result.diagnostics,
+        ) {
+            Ok(freezed_class) => result.classes.push(freezed_class),
+            Err(diagnostic) => result.diagnostics.push(diagnostic),
+        }
+    }
+
+    result
+}
+
+fn parse_class(
+    class_declaration: tree_sitter::Node,
+    annotations: Vec<Annotation>,
+    code: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<ClassDefinition, Diagnostic> {
+    let class_name = class_declaration
+        .child_by_field_name("name")
+        .map(|e| get_text(e, code))
+        .ok_or_else(|| unexpected_node(class_declaration, code, "a class name"))?;
+
+    let mut modifier_cursor = class_declaration.walk();
+    let is_abstract = class_declaration
+        .children(&mut modifier_cursor)
+        .any(|e| matches!(e.kind(), "abstract" | "sealed"));
+
+    let mut mixins = Vec::new();
+    let mut superclass = None;
+    if let Some(superclass_node) = class_declaration.child_by_field_name("superclass") {
+        let mut superclass_cursor = superclass_node.walk();
+        for child in superclass_node.named_children(&mut superclass_cursor) {
+            if child.kind() == "mixins" {
+                mixins = parse_type_list(child, code);
+            } else if superclass.is_none() && child.kind() != "comment" {
+                let (dart_type, _skip) = parse_type(child, code);
+                superclass = Some(dart_type);
             }
-            None
-        };
+        }
+    }
 
-        let mut redirecting_constructors = Vec::new();
-        let mut json_constructor = None;
-        let mut unnamed_constructor = None;
-        let mut class_query = QueryCursor::new();
-
-        let mut executed_query =
-            class_query.matches(&members_q, class_declaration, code.as_bytes());
-
-        while let Some(inner_declaration_group) = executed_query.next() {
-            assert!(inner_declaration_group.captures.len() == 1);
-            let capture = inner_declaration_group.captures.first().unwrap();
-            let x = parse_class_declaration(capture.node, code);
-            if let Some(y) = x {
-                match y {
-                    DeclarationParseResult::Redirected(redirected_constructor) => {
-                        redirecting_constructors.push(redirected_constructor);
-                    }
-                    DeclarationParseResult::Json(redirected_constructor) => {
-                        json_constructor = Some(redirected_constructor);
-                    }
-                    DeclarationParseResult::Unnamed(redirected_constructor) => {
-                        unnamed_constructor = Some(redirected_constructor);
-                    }
+    let interfaces = class_declaration
+        .child_by_field_name("interfaces")
+        .map(|e| parse_type_list(e, code))
+        .unwrap_or_default();
+
+    let mut redirecting_constructors = Vec::new();
+    let mut json_constructor = None;
+    let mut unnamed_constructor = None;
+    let mut generative_constructor = None;
+    let mut fields = Vec::new();
+    let mut class_query = QueryCursor::new();
+
+    let mut executed_query = class_query.matches(&members_q, class_declaration, code.as_bytes());
+
+    while let Some(inner_declaration_group) = executed_query.next() {
+        assert!(inner_declaration_group.captures.len() == 1);
+        let capture = inner_declaration_group.captures.first().unwrap();
+        let x = parse_class_declaration(capture.node, code)?;
+        if let Some(y) = x {
+            match y {
+                DeclarationParseResult::Redirected(redirected_constructor) => {
+                    redirecting_constructors.push(redirected_constructor);
+                }
+                DeclarationParseResult::Json(redirected_constructor) => {
+                    json_constructor = Some(redirected_constructor);
+                }
+                DeclarationParseResult::Unnamed(redirected_constructor) => {
+                    unnamed_constructor = Some(redirected_constructor);
+                }
+                DeclarationParseResult::Generative(constructor) => {
+                    generative_constructor = Some(constructor);
+                }
+                DeclarationParseResult::Fields(declared_fields) => {
+                    fields.extend(declared_fields);
                 }
             }
         }
+    }
 
+    if let Some(constructor) = generative_constructor.as_mut() {
+        resolve_initializing_formals(&mut constructor.parameters, &fields);
+    }
 
-        let freezed_class = ClassDefinition {
-            name: class_name.to_string(),
-            gen_form,
-            mixins: mixin.into_iter().collect(),
-            redirecting_constructors,
-            json_constructor,
-            unnamed_constructor,
+    let span = get_span(class_declaration);
+    let is_freezed = annotations.iter().any(Annotation::is_freezed);
+    if redirecting_constructors.is_empty() && generative_constructor.is_none() {
+        let message = if is_freezed {
+            format!("freezed class `{class_name}` has neither a factory nor an unnamed constructor")
+        } else {
+            format!("json_serializable class `{class_name}` has no unnamed constructor")
         };
+        return Err(Diagnostic::error("missing-constructor", message, span));
+    }
+
+    let parameters = redirecting_constructors
+        .iter()
+        .chain(&generative_constructor)
+        .flat_map(|e| e.parameters.get_all_params());
+    for parameter in parameters {
+        if let Some(default) = parameter
+            .annotations
+            .iter()
+            .find(|e| e.name == "Default" && e.arguments.len() != 1)
+        {
+            return Err(Diagnostic::error(
+                "invalid-default",
+                format!(
+                    "`@Default` of `{}` in `{class_name}` needs exactly one value",
+                    parameter.name
+                ),
+                default.span,
+            ));
+        }
+    }
 
-        output_freezed_classes.push(freezed_class);
+    let freezed_class = ClassDefinition {
+        span,
+        name: class_name,
+        annotations,
+        documentation: parse_documentation(class_declaration, code),
+        is_abstract,
+        mixins,
+        interfaces,
+        superclass,
+        redirecting_constructors,
+        json_constructor,
+        unnamed_constructor,
+        generative_constructor,
+        fields,
+    };
+
+    if is_freezed
+        && !freezed_class
+            .mixins
+            .iter()
+            .any(|e| e.name == freezed_class.freezed_mixin().name)
+    {
+        diagnostics.push(Diagnostic::warning(
+            "missing-mixin",
+            format!(
+                "`{0}` doesn't mix in `_${0}`, the generated code won't be used",
+                freezed_class.name
+            ),
+            span,
+        ));
     }
 
-    output_freezed_classes
+    Ok(freezed_class)
 }
 
 enum RedirectingFactoryItems {
@@ -141,13 +371,19 @@ enum DeclarationParseResult {
     Redirected(RedirectedConstructor),
     Json(RedirectedConstructor),
     Unnamed(RedirectedConstructor),
+    Generative(RedirectedConstructor),
+    Fields(Vec<PositionalParameter>),
 }
 
-fn parse_class_declaration(node: tree_sitter::Node, code: &str) -> Option<DeclarationParseResult> {
-    let first_child = node.child(0).unwrap();
+fn parse_class_declaration(
+    node: tree_sitter::Node,
+    code: &str,
+) -> Result<Option<DeclarationParseResult>, Diagnostic> {
+    let Some(first_child) = node.child(0) else {
+        return Ok(None);
+    };
 
     if first_child.kind() == "redirecting_factory_constructor_signature" {
-        assert!(node.child_count() == 1);
         let redirecting_constructor = first_child;
 
         // redirecting_factory_constructor_signature: $ => seq(
@@ -180,12 +416,16 @@ fn parse_class_declaration(node: tree_sitter::Node, code: &str) -> Option<Declar
                         stage = RedirectingFactoryItems::FactoryKeyword;
                     }
                     RedirectingFactoryItems::FactoryKeyword => {
-                        assert!(child.kind() == "factory");
+                        if child.kind() != "factory" {
+                            return Err(unexpected_node(child, code, "`factory`"));
+                        }
                         was_processed = 1;
                         stage = RedirectingFactoryItems::ClassName;
                     }
                     RedirectingFactoryItems::ClassName => {
-                        assert!(child.kind() == "identifier");
+                        if child.kind() != "identifier" {
+                            return Err(unexpected_node(child, code, "the class name"));
+                        }
                         class_name = child.utf8_text(code.as_bytes()).unwrap().to_owned();
                         was_processed = 1;
                         stage = RedirectingFactoryItems::ConstructorName;
@@ -201,11 +441,11 @@ fn parse_class_declaration(node: tree_sitter::Node, code: &str) -> Option<Declar
                         } else if child.kind() == "formal_parameter_list" {
                             stage = RedirectingFactoryItems::FormalParameterList;
                         } else {
-                            panic!("Wrong syntax");
+                            return Err(unexpected_node(child, code, "a constructor name"));
                         }
                     }
                     RedirectingFactoryItems::FormalParameterList => {
-                        arguments = parse_formal_parameter_list(child, code);
+                        arguments = parse_formal_parameter_list(child, code)?;
                         was_processed = 1;
                         stage = RedirectingFactoryItems::AssignedConstructor;
                     }
@@ -223,49 +463,244 @@ fn parse_class_declaration(node: tree_sitter::Node, code: &str) -> Option<Declar
             was_processed -= 1;
         }
         let constructor = RedirectedConstructor {
+            span: get_span(node),
+            documentation: parse_documentation(node, code),
+            annotations: parse_member_annotations(node, code),
             class_name,
             is_const,
             constructor_name,
             parameters: arguments,
-            assigned_type: assigned_type.unwrap(),
+            assigned_type: assigned_type.ok_or_else(|| {
+                unexpected_node(redirecting_constructor, code, "a redirected class")
+            })?,
         };
-        return Some(DeclarationParseResult::Redirected(constructor));
+        return Ok(Some(DeclarationParseResult::Redirected(constructor)));
     } else if node.kind() == "factory_constructor_signature" {
-        if let Some(child) = node.named_child(1)
-            && get_text(child, code) == "fromJson"
-        {}
+        let mut cursor = node.walk();
+        let names: Vec<String> = node
+            .named_children(&mut cursor)
+            .filter(|e| e.kind() == "identifier")
+            .map(|e| get_text(e, code))
+            .collect();
+
+        // Helpers like `factory Foo.empty() => ...` have nothing to do with JSON
+        if names.get(1).map(String::as_str) != Some("fromJson") {
+            return Ok(None);
+        }
+        let class_name = names.first().cloned().unwrap_or_default();
+
+        // `=> _$FooFromJsonCustom(json)` picks the name of the generated function
+        let signature = node.parent().unwrap_or(node);
+        let mut body = signature.next_named_sibling();
+        while let Some(sibling) = body
+            && sibling.kind().ends_with("comment")
+        {
+            body = sibling.next_named_sibling();
+        }
+        let from_json_function = body
+            .filter(|e| e.kind() == "function_body")
+            .and_then(|e| parse_called_function(&get_text(e, code)))
+            .filter(|e| e.starts_with("_$"))
+            .unwrap_or_else(|| format!("_${class_name}FromJson"));
+
         let constructor = RedirectedConstructor {
-            class_name: "fromJson".to_string(),
+            span: get_span(signature),
+            annotations: parse_member_annotations(signature, code),
+            class_name,
+            constructor_name: Some("fromJson".to_string()),
+            assigned_type: DartType {
+                name: from_json_function,
+                ..Default::default()
+            },
             ..Default::default()
         };
 
-        return Some(DeclarationParseResult::Json(constructor));
+        return Ok(Some(DeclarationParseResult::Json(constructor)));
+    } else if node.kind() == "constructor_signature" {
+        // Generative constructor with a body
+        return parse_generative_constructor(node, code);
     } else if ["constructor_signature", "constant_constructor_signature"]
         .contains(&first_child.kind())
     {
-        let is_const = first_child.kind() == "constant_constructor_signature";
-        if is_const {
-            assert_eq!(get_text(first_child.named_child(2).unwrap(), code), "_");
+        return parse_generative_constructor(first_child, code);
+    } else if node.kind() == "declaration" {
+        let mut cursor = node.walk();
+        let is_static = node.children(&mut cursor).any(|e| e.kind() == "static");
+        let identifiers = node
+            .named_children(&mut cursor)
+            .find(|e| e.kind() == "initialized_identifier_list");
+
+        if let Some(identifiers) = identifiers
+            && !is_static
+        {
+            return Ok(Some(DeclarationParseResult::Fields(
+                parse_field_declaration(node, identifiers, code),
+            )));
+        }
+        return Ok(None);
+    }
+
+    Err(unexpected_node(node, code, "a class member"))
+}
+
+/// Name of the function a `=> f(json)` or `{ return f(json); }` body forwards to
+fn parse_called_function(body: &str) -> Option<String> {
+    let (_, expression) = body
+        .split_once("=>")
+        .or_else(|| body.split_once("return"))?;
+    let expression = skip_comments(expression);
+    let name_length = expression
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'))
+        .unwrap_or(expression.len());
+
+    let (name, rest) = expression.split_at(name_length);
+    if name.is_empty() || !skip_comments(rest).starts_with('(') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// `text` from its first token, past whitespace and comments
+fn skip_comments(mut text: &str) -> &str {
+    loop {
+        text = text.trim_start();
+        if let Some(rest) = text.strip_prefix("//") {
+            text = rest.split_once('\n').map_or("", |e| e.1);
+        } else if let Some(rest) = text.strip_prefix("/*") {
+            text = rest.split_once("*/").map_or("", |e| e.1);
         } else {
-            assert_eq!(get_text(first_child.named_child(1).unwrap(), code), "_");
+            return text;
         }
+    }
+}
 
-        let constructor = RedirectedConstructor {
-            class_name: "_".to_string(),
-            is_const,
-            ..Default::default()
+/// Handles both `Foo._()`, which lets the class declare its own members, and the
+/// unnamed `Foo(...)` constructor of classes declaring their fields directly.
+fn parse_generative_constructor(
+    signature: tree_sitter::Node,
+    code: &str,
+) -> Result<Option<DeclarationParseResult>, Diagnostic> {
+    let is_const = signature.kind() == "constant_constructor_signature";
+
+    let mut names = Vec::new();
+    let mut parameters = ParameterList::default();
+    let mut cursor = signature.walk();
+    for child in signature.named_children(&mut cursor) {
+        match child.kind() {
+            "identifier" => names.push(get_text(child, code)),
+            "formal_parameter_list" => parameters = parse_formal_parameter_list(child, code)?,
+            _ => {}
+        }
+    }
+
+    let member = signature.parent().unwrap_or(signature);
+    let mut constructor = RedirectedConstructor {
+        span: get_span(signature),
+        documentation: parse_documentation(member, code),
+        class_name: names.first().cloned().unwrap_or_default(),
+        constructor_name: names.get(1).cloned(),
+        is_const,
+        ..Default::default()
+    };
+
+    Ok(match constructor.constructor_name.as_deref() {
+        Some("_") => {
+            constructor.class_name = "_".to_string();
+            Some(DeclarationParseResult::Unnamed(constructor))
+        }
+        None => {
+            constructor.parameters = parameters;
+            Some(DeclarationParseResult::Generative(constructor))
+        }
+        // Other named constructors aren't used by the generator
+        Some(_) => None,
+    })
+}
+
+/// `this.name` parameters take their type and docs from the field they initialize,
+/// and annotations placed on the field (e.g. `@JsonKey`) apply to the parameter.
+fn resolve_initializing_formals(parameters: &mut ParameterList, fields: &[PositionalParameter]) {
+    let positional = parameters.positional_parameters.iter_mut().map(|e| {
+        (
+            &e.name,
+            &mut e.dart_type,
+            &mut e.annotations,
+            &mut e.documentation,
+        )
+    });
+    let named = parameters.named_parameters.iter_mut().map(|e| {
+        (
+            &e.name,
+            &mut e.dart_type,
+            &mut e.annotations,
+            &mut e.documentation,
+        )
+    });
+
+    for (name, dart_type, annotations, documentation) in positional.chain(named) {
+        let Some(field) = fields.iter().find(|e| &e.name == name) else {
+            continue;
         };
+        if dart_type.name.is_empty() {
+            *dart_type = field.dart_type.clone();
+        }
+        if documentation.is_none() {
+            documentation.clone_from(&field.documentation);
+        }
+        for annotation in &field.annotations {
+            if !annotations.contains(annotation) {
+                annotations.push(annotation.clone());
+            }
+        }
+    }
+}
 
-        return Some(DeclarationParseResult::Unnamed(constructor));
-    } else if node.kind() == "declaration" {
-        return None;
+/// `final Type a, b;` style instance fields
+fn parse_field_declaration(
+    node: tree_sitter::Node,
+    identifiers: tree_sitter::Node,
+    code: &str,
+) -> Vec<PositionalParameter> {
+    let mut dart_type = DartType::default();
+    let mut cursor = node.walk();
+    // `late` comes before the type, as a node of its own in some grammar versions
+    if let Some(type_node) = node.named_children(&mut cursor).find(|e| {
+        ![
+            "late",
+            "final_builtin",
+            "const_builtin",
+            "inferred_type",
+            "initialized_identifier_list",
+            "comment",
+            "documentation_comment",
+        ]
+        .contains(&e.kind())
+            && get_text(*e, code) != "late"
+    }) {
+        dart_type = parse_type(type_node, code).0;
     }
 
-    todo!(
-        "Unimplemented: first: {}, node: {}",
-        first_child.kind(),
-        node.kind()
-    );
+    let annotations = parse_member_annotations(node, code);
+    let documentation = parse_documentation(node, code);
+    let mut fields = Vec::new();
+    let mut cursor = identifiers.walk();
+    for identifier in identifiers.named_children(&mut cursor) {
+        let Some(name) = identifier
+            .named_child(0)
+            .filter(|_| identifier.kind() == "initialized_identifier")
+        else {
+            continue;
+        };
+        fields.push(PositionalParameter {
+            span: get_span(identifier),
+            documentation: documentation.clone(),
+            name: get_text(name, code),
+            dart_type: dart_type.clone(),
+            annotations: annotations.clone(),
+        });
+    }
+
+    fields
 }
 
 // FORMAL PARAMETER GRAMMAR
@@ -308,8 +743,13 @@ enum FormalParameterSteps {
     Finish,
 }
 
-fn parse_formal_parameter_list(node: tree_sitter::Node, code: &str) -> ParameterList {
-    assert!(node.kind() == "formal_parameter_list");
+fn parse_formal_parameter_list(
+    node: tree_sitter::Node,
+    code: &str,
+) -> Result<ParameterList, Diagnostic> {
+    if node.kind() != "formal_parameter_list" {
+        return Err(unexpected_node(node, code, "a parameter list"));
+    }
     let mut positional_parameters: Vec<PositionalParameter> = Vec::new();
     let mut named_parameters: Vec<NamedParameter> = Vec::new();
 
@@ -319,22 +759,21 @@ fn parse_formal_parameter_list(node: tree_sitter::Node, code: &str) -> Parameter
 
     while let Some(child) = current_node {
         if child.kind() == "formal_parameter" {
-            positional_parameters.push(parse_formal_parameter(child, code));
+            positional_parameters.push(parse_formal_parameter(child, code)?);
             current_node = child.next_named_sibling();
-        } else {
-            assert!(child.kind() == "optional_formal_parameters");
+        } else if child.kind().ends_with("comment") {
+            current_node = child.next_named_sibling();
+        } else if child.kind() == "optional_formal_parameters" {
             break;
+        } else {
+            return Err(unexpected_node(child, code, "a parameter"));
         }
     }
 
     if let Some(optional_node) = current_node {
-        assert_eq!(optional_node.kind(), "optional_formal_parameters");
-
         let mut cursor = optional_node.walk();
         let mut current_state = FormalParameterSteps::OpenBracket;
 
-        let mut processing_argument = false;
-
         let mut current_argument: Option<NamedParameter> = None;
 
         for child in optional_node.children(&mut cursor) {
@@ -344,45 +783,56 @@ fn parse_formal_parameter_list(node: tree_sitter::Node, code: &str) -> Parameter
                     break;
                 }
 
+                // `///` lines between parameters document the following one
+                if child.kind() == "documentation_comment" {
+                    if let FormalParameterSteps::Annotations = current_state {
+                        let argument = current_argument.get_or_insert_with(|| NamedParameter {
+                            span: get_span(child),
+                            ..Default::default()
+                        });
+                        let line = get_text(child, code);
+                        match argument.documentation.as_mut() {
+                            Some(documentation) => {
+                                documentation.push('\n');
+                                documentation.push_str(&line);
+                            }
+                            None => argument.documentation = Some(line),
+                        }
+                    }
+                    break;
+                }
+
                 match current_state {
                     FormalParameterSteps::OpenBracket => {
-                        assert_eq!(child.kind(), "{");
-                        processing_argument = true;
+                        if child.kind() == "[" {
+                            return Err(Diagnostic::error(
+                                "unsupported-syntax",
+                                "optional positional parameters aren't supported, use named parameters",
+                                get_span(optional_node),
+                            ));
+                        } else if child.kind() != "{" {
+                            return Err(unexpected_node(child, code, "`{`"));
+                        }
                         was_processed = true;
                         current_state = FormalParameterSteps::Annotations;
                     }
                     FormalParameterSteps::Annotations => {
-                        assert!(processing_argument);
-
                         if child.kind() == "}" {
+                            // A `///` comment after the last parameter documents nothing
+                            current_argument = None;
                             was_processed = true;
                             current_state = FormalParameterSteps::CloseBracket;
                             continue;
                         }
 
-                        if current_argument.is_none() {
-                            current_argument = Some(NamedParameter::default());
-                        }
+                        let argument = current_argument.get_or_insert_with(|| NamedParameter {
+                            span: get_span(child),
+                            ..Default::default()
+                        });
 
                         if child.kind() == "annotation" {
                             was_processed = true;
-                            let name_node = child.child_by_field_name("name").unwrap();
-                            let mut argument_node = name_node
-                                .next_named_sibling()
-                                .and_then(|e| e.named_child(0));
-
-                            let name = get_text(name_node, code);
-                            let mut arguments = Vec::new();
-                            while let Some(arg) = argument_node {
-                                arguments.push(get_text(arg, code));
-                                argument_node = arg.next_named_sibling();
-                            }
-
-                            current_argument
-                                .as_mut()
-                                .unwrap()
-                                .annotations
-                                .push(Annotation { name, arguments });
+                            argument.annotations.push(parse_annotation(child, code));
                         } else {
                             was_processed = false;
                             current_state = FormalParameterSteps::Required;
@@ -396,77 +846,119 @@ fn parse_formal_parameter_list(node: tree_sitter::Node, code: &str) -> Parameter
                         current_state = FormalParameterSteps::FormalParameter;
                     }
                     FormalParameterSteps::FormalParameter => {
-                        assert_eq!(child.kind(), "formal_parameter");
-                        let x = parse_formal_parameter(child, code);
+                        let x = parse_formal_parameter(child, code)?;
 
-                        current_argument.as_mut().unwrap().name = x.name;
-                        current_argument.as_mut().unwrap().dart_type = x.dart_type;
-                        current_argument
-                            .as_mut()
-                            .unwrap()
-                            .annotations
-                            .extend(x.annotations);
+                        // The span covers leading metadata and `required` too
+                        let argument = current_argument.as_mut().unwrap();
+                        argument.span.end_byte = x.span.end_byte;
+                        argument.span.end = x.span.end;
+                        argument.name = x.name;
+                        argument.dart_type = x.dart_type;
+                        argument.annotations.extend(x.annotations);
 
                         was_processed = true;
                         current_state = FormalParameterSteps::DefaultValue;
                     }
                     FormalParameterSteps::DefaultValue => {
-                        if child.kind() == "=" {
+                        if child.kind() == "=" || child.kind() == ":" {
                             was_processed = true;
                         } else if child.kind() == "," {
                             was_processed = true;
                             current_state = FormalParameterSteps::Annotations;
-                            named_parameters.push(current_argument.unwrap());
-                            current_argument = None;
+                            named_parameters.extend(current_argument.take());
                         } else if child.kind() == "}" {
                             was_processed = false;
                             current_state = FormalParameterSteps::CloseBracket;
+                        } else if child.is_named() {
+                            // `= expression` or the legacy `: expression`
+                            let argument = current_argument.as_mut().unwrap();
+                            argument.default = Some(get_text(child, code));
+                            argument.span.end_byte = child.end_byte();
+                            argument.span.end = get_span(child).end;
+                            was_processed = true;
                         } else {
-                            // TODO, default value
-                            unreachable!("What the fuck are you: {}", child.kind());
+                            return Err(unexpected_node(child, code, "`,` or `}`"));
                         }
                     }
                     FormalParameterSteps::CloseBracket => {
-                        assert_eq!(child.kind(), "}");
+                        if child.kind() != "}" {
+                            return Err(unexpected_node(child, code, "`}`"));
+                        }
                         was_processed = true;
                         current_state = FormalParameterSteps::Finish;
                     }
-                    FormalParameterSteps::Finish => unreachable!(),
+                    FormalParameterSteps::Finish => {
+                        return Err(unexpected_node(child, code, "the end of the parameters"));
+                    }
                 }
             }
         }
 
-        if let Some(argument) = current_argument {
-            named_parameters.push(argument);
-        }
-        /* current_argument = None; */
+        named_parameters.extend(current_argument);
     }
 
-    ParameterList {
+    Ok(ParameterList {
         positional_parameters,
         named_parameters,
-    }
+    })
 }
 
-fn parse_formal_parameter(node: tree_sitter::Node, code: &str) -> PositionalParameter {
-    assert_eq!(node.kind(), "formal_parameter");
+fn parse_formal_parameter(
+    node: tree_sitter::Node,
+    code: &str,
+) -> Result<PositionalParameter, Diagnostic> {
+    if node.kind() != "formal_parameter" {
+        return Err(unexpected_node(node, code, "a parameter"));
+    }
 
-    let current_node = node.named_child(0).unwrap();
-    let annotations = parse_annotations(current_node, code);
+    let annotations = node
+        .named_child(0)
+        .map(|e| parse_annotations(e, code))
+        .unwrap_or_default();
     let mut total_skip = annotations.len();
 
-    let (argument_type, skipped) = parse_type(node.named_child(total_skip).unwrap(), code);
-    total_skip += skipped;
-    let identifier = node.named_child(total_skip).unwrap();
+    // `this.name` and `super.name` keep their type and name one level deeper
+    let mut parameter_node = node;
+    if let Some(child) = node.named_child(total_skip)
+        && ["constructor_param", "super_formal_parameter"].contains(&child.kind())
+    {
+        parameter_node = child;
+        total_skip = 0;
+    }
 
-    assert_eq!(identifier.kind(), "identifier");
+    let mut argument_type = DartType::default();
+    while let Some(child) = parameter_node.named_child(total_skip) {
+        match child.kind() {
+            "final_builtin"
+            | "const_builtin"
+            | "inferred_type"
+            | "this"
+            | "super"
+            | "comment"
+            | "documentation_comment" => {
+                total_skip += 1;
+            }
+            "identifier" => break,
+            _ => {
+                let (dart_type, skipped) = parse_type(child, code);
+                argument_type = dart_type;
+                total_skip += skipped.max(1);
+            }
+        }
+    }
+
+    let identifier = parameter_node
+        .named_child(total_skip)
+        .ok_or_else(|| unexpected_node(node, code, "a parameter name"))?;
     let argument_name = get_text(identifier, code);
 
-    PositionalParameter {
+    Ok(PositionalParameter {
+        span: get_span(node),
+        documentation: parse_documentation(node, code),
         name: argument_name,
         dart_type: argument_type,
         annotations,
-    }
+    })
 }
 
 //  - Function type ???
@@ -478,24 +970,12 @@ fn parse_annotations(node: tree_sitter::Node, code: &str) -> Vec<Annotation> {
         return Vec::new();
     }
 
-    assert_eq!(node.kind(), "annotation");
     let mut annotations = Vec::new();
     let mut cursor_node = Some(node);
 
     while let Some(node) = cursor_node {
         if node.kind() == "annotation" {
-            let name_node = node.child_by_field_name("name").unwrap();
-            let mut argument_node = name_node
-                .next_named_sibling()
-                .and_then(|e| e.named_child(0));
-
-            let name = get_text(name_node, code);
-            let mut arguments = Vec::new();
-            while let Some(arg) = argument_node {
-                arguments.push(get_text(arg, code));
-                argument_node = arg.next_named_sibling();
-            }
-            annotations.push(Annotation { name, arguments });
+            annotations.push(parse_annotation(node, code));
             cursor_node = node.next_named_sibling();
         } else {
             break;
@@ -505,7 +985,115 @@ fn parse_annotations(node: tree_sitter::Node, code: &str) -> Vec<Annotation> {
     annotations
 }
 
-fn parse_type(node: tree_sitter::Node, code: &str) -> (DartType, usize) {
+/// Class metadata can be interleaved with comments and modifiers, so every direct
+/// `annotation` child is collected instead of stopping at the first other node.
+pub(crate) fn parse_class_annotations(node: tree_sitter::Node, code: &str) -> Vec<Annotation> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .filter(|child| child.kind() == "annotation")
+        .map(|child| parse_annotation(child, code))
+        .collect()
+}
+
+pub(crate) fn parse_annotation(node: tree_sitter::Node, code: &str) -> Annotation {
+    let name = node
+        .child_by_field_name("name")
+        .map(|e| get_text(e, code))
+        .unwrap_or_default();
+
+    let mut type_arguments = Vec::new();
+    let mut arguments = Vec::new();
+    let mut cursor = node.walk();
+    if let Some(type_arguments_node) = node
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "type_arguments")
+    {
+        type_arguments = parse_type_list(type_arguments_node, code);
+    }
+
+    if let Some(arguments_node) = node
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "arguments")
+    {
+        let mut argument_cursor = arguments_node.walk();
+        for argument in arguments_node.named_children(&mut argument_cursor) {
+            if argument.kind() != "comment" && argument.kind() != "documentation_comment" {
+                arguments.push(get_text(argument, code));
+            }
+        }
+    }
+
+    Annotation {
+        span: get_span(node),
+        source: get_text(node, code),
+        name,
+        type_arguments,
+        arguments,
+    }
+}
+
+/// Metadata of class members is stored as preceding siblings in the class body,
+/// not as children of the member itself.
+pub(crate) fn parse_member_annotations(node: tree_sitter::Node, code: &str) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    let mut current_node = node.prev_sibling();
+
+    while let Some(sibling) = current_node {
+        match sibling.kind() {
+            "annotation" => annotations.push(parse_annotation(sibling, code)),
+            "comment" | "documentation_comment" => {}
+            _ => break,
+        }
+        current_node = sibling.prev_sibling();
+    }
+
+    annotations.reverse();
+    annotations
+}
+
+/// Collects the `///` lines directly above a node, skipping over annotations and
+/// regular comments in between.
+fn parse_documentation(node: tree_sitter::Node, code: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut current_node = node.prev_sibling();
+
+    while let Some(sibling) = current_node {
+        match sibling.kind() {
+            "documentation_comment" => lines.push(get_text(sibling, code)),
+            "comment" | "annotation" => {}
+            _ => break,
+        }
+        current_node = sibling.prev_sibling();
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+/// Parses the comma separated types of `with`, `implements` or `<...>` clauses
+pub(crate) fn parse_type_list(node: tree_sitter::Node, code: &str) -> Vec<DartType> {
+    let mut types = Vec::new();
+    let mut current_child = 0;
+
+    while current_child < node.named_child_count() {
+        let child = node.named_child(current_child).unwrap();
+        let (dart_type, skipped) = parse_type(child, code);
+        if skipped == 0 {
+            current_child += 1;
+            continue;
+        }
+        types.push(dart_type);
+        current_child += skipped;
+    }
+
+    types
+}
+
+pub(crate) fn parse_type(node: tree_sitter::Node, code: &str) -> (DartType, usize) {
     let mut processed = 0;
     let mut name: String = String::new();
     let mut type_arguments = Vec::new();
@@ -527,19 +1115,22 @@ fn parse_type(node: tree_sitter::Node, code: &str) -> (DartType, usize) {
 
         processed += 1;
         current_node = node.next_named_sibling();
+
+        // `p.Address` from a prefixed import is two identifiers joined by a dot
+        if let Some(next) = current_node
+            && next.kind() == "type_identifier"
+            && code[node.end_byte()..next.start_byte()].trim() == "."
+        {
+            name = format!("{name}.{}", get_text(next, code));
+            processed += 1;
+            current_node = next.next_named_sibling();
+        }
     }
 
     if let Some(node) = current_node
         && node.kind() == "type_arguments"
     {
-        //let arguments_node = current_node.named_child(0).unwrap();
-        let mut current_child = 0;
-
-        while current_child < node.named_child_count() {
-            let (dart_type, skipped) = parse_type(node.named_child(current_child).unwrap(), code);
-            type_arguments.push(dart_type);
-            current_child += skipped;
-        }
+        type_arguments = parse_type_list(node, code);
         processed += 1;
         current_node = node.next_named_sibling();
     }
@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::package_config::normalize_path;
+
+/// Which directories the traversal leaves out
+#[derive(Debug, Clone)]
+pub struct TraversalOptions {
+    /// Nested `example/` and `examples/` packages, skipped unless asked for
+    pub include_examples: bool,
+    /// Directories skipped in package roots, build outputs and pub's cache by default
+    pub skipped_directories: Vec<String>,
+}
+
+impl Default for TraversalOptions {
+    fn default() -> Self {
+        Self {
+            include_examples: false,
+            skipped_directories: vec!["build".to_owned(), ".dart_tool".to_owned()],
+        }
+    }
+}
+
+/// The dart sources under a target, generated files left out
+#[derive(Debug, Default, Clone)]
+pub struct ProjectFiles {
+    /// Each file with the index of its package in `package_roots`, `None` being the
+    /// package the target itself is in, if any
+    pub files: Vec<(PathBuf, Option<usize>)>,
+    /// Every nested directory with a `pubspec.yaml`
+    pub package_roots: Vec<PathBuf>,
+}
+
+impl ProjectFiles {
+    pub fn collect(path: &Path, options: &TraversalOptions) -> Self {
+        let mut project_files = Self::default();
+        project_files.traverse_directory(path, None, options);
+        project_files
+    }
+
+    fn traverse_directory(
+        &mut self,
+        path: &Path,
+        package: Option<usize>,
+        options: &TraversalOptions,
+    ) {
+        let Ok(directory) = fs::read_dir(path) else {
+            eprintln!("[E] Error reading directory {path:?}");
+            return;
+        };
+        let is_package_root = path.join("pubspec.yaml").is_file();
+
+        for entry in directory {
+            let Ok(entry) = entry else {
+                eprintln!("[E] Error reading directory {path:?}");
+                continue;
+            };
+
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|e| e.to_str()) else {
+                continue;
+            };
+            if path.is_file() {
+                if name.ends_with(".dart")
+                    && !name.ends_with(".g.dart")
+                    && !name.ends_with(".freezed.dart")
+                {
+                    self.files.push((path, package));
+                }
+                continue;
+            }
+
+            if !path.is_dir() {
+                continue;
+            }
+            if is_package_root && options.skipped_directories.iter().any(|e| e == name) {
+                continue;
+            }
+
+            let mut package = package;
+            if path.join("pubspec.yaml").is_file() {
+                if !options.include_examples && (name == "example" || name == "examples") {
+                    continue;
+                }
+                self.package_roots.push(normalize_path(&path));
+                package = Some(self.package_roots.len() - 1);
+            }
+            self.traverse_directory(&path, package, options);
+        }
+    }
+}
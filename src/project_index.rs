@@ -0,0 +1,927 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use tree_sitter::Parser;
+
+use crate::dart_types::{DartType, Position, Span, split_top_level};
+use crate::package_config::{PackageConfig, normalize_path};
+use crate::parser::{
+    DART_TS, get_span, get_text, parse_annotation, parse_class_annotations, parse_type,
+    parse_type_list,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Class,
+    Enum,
+    Typedef,
+    ExtensionType,
+    Mixin,
+    /// A top-level `const`, indexed for the annotations naming it
+    Constant,
+}
+
+/// `extension type UserId._(String value)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Representation {
+    /// `UserId._`, what creates the extension type from its representation
+    pub constructor: String,
+    pub field: String,
+    pub dart_type: DartType,
+}
+
+/// A top-level type declared somewhere in the scanned Dart files
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub path: PathBuf,
+    pub span: Span,
+    pub type_parameters: Vec<String>,
+    /// `extends`, `with` and `implements` clauses
+    pub supertypes: Vec<DartType>,
+    pub is_freezed: bool,
+    pub is_json_serializable: bool,
+    /// Declares `factory X.fromJson(...)` or a static `fromJson`
+    pub has_from_json: bool,
+    /// Declares `toJson()` or gets it generated
+    pub has_to_json: bool,
+    /// Enum constants with the value they're serialized to, `@JsonValue` included
+    pub enum_values: Vec<(String, String)>,
+    /// The field an extension type wraps
+    pub representation: Option<Representation>,
+    /// `typedef Json = Map<String, dynamic>;`, over `type_parameters` for generic ones.
+    /// `None` for function typedefs.
+    pub aliased_type: Option<DartType>,
+    /// The class a constant is an instance of, `EpochConverter` for
+    /// `const epochConverter = EpochConverter();`
+    pub value_type: Option<DartType>,
+}
+
+impl Symbol {
+    fn new(name: String, kind: SymbolKind, path: &Path, span: Span) -> Self {
+        Self {
+            name,
+            kind,
+            path: path.to_owned(),
+            span,
+            type_parameters: Vec::new(),
+            supertypes: Vec::new(),
+            is_freezed: false,
+            is_json_serializable: false,
+            has_from_json: false,
+            has_to_json: false,
+            enum_values: Vec::new(),
+            representation: None,
+            aliased_type: None,
+            value_type: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectiveKind {
+    Import,
+    Export,
+    Part,
+}
+
+/// An `import`, `export` or `part` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    pub kind: DirectiveKind,
+    pub uri: String,
+    /// `as p`
+    pub prefix: Option<String>,
+    pub show: Vec<String>,
+    pub hide: Vec<String>,
+}
+
+impl Directive {
+    /// Whether the combinators let `name` through
+    pub fn exposes(&self, name: &str) -> bool {
+        (self.show.is_empty() || self.show.iter().any(|e| e == name))
+            && !self.hide.iter().any(|e| e == name)
+    }
+}
+
+/// The project types visible in one file, through its own declarations and its imports
+#[derive(Debug, Default, Clone)]
+pub struct Scope {
+    /// Name as written in the file, `p.Address` for prefixed imports, to the files
+    /// declaring it
+    names: HashMap<String, Vec<PathBuf>>,
+    /// Every import was resolved, so a type missing from the scope isn't a project type
+    pub is_complete: bool,
+}
+
+impl Scope {
+    pub fn declaring_files(&self, name: &str) -> &[PathBuf] {
+        self.names.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn add(&mut self, name: String, path: PathBuf) {
+        let paths = self.names.entry(name).or_default();
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+}
+
+/// Every class, enum, typedef, extension type, mixin and constant of the project, keyed by name,
+/// for the decisions a single file doesn't have enough information for.
+#[derive(Debug, Default)]
+pub struct ProjectIndex {
+    symbols: HashMap<String, Vec<Symbol>>,
+    /// Names declared by each file
+    declarations: HashMap<PathBuf, Vec<String>>,
+    directives: HashMap<PathBuf, Vec<Directive>>,
+    /// Files that went through tree-sitter, the rest couldn't declare anything
+    parsed_files: usize,
+}
+
+/// A file without any of these, nor a line starting with `const`, neither declares an
+/// indexed type nor brings one in with `export` or `part`, so it isn't parsed. Its
+/// imports only matter when it's generated, and then it has a class.
+const INDEXED_KEYWORDS: &[&str] = &["class", "enum", "mixin", "typedef", "export", "part"];
+
+impl ProjectIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn parsed_files(&self) -> usize {
+        self.parsed_files
+    }
+
+    pub fn insert(&mut self, symbol: Symbol) {
+        self.declarations
+            .entry(symbol.path.clone())
+            .or_default()
+            .push(symbol.name.clone());
+        self.symbols
+            .entry(symbol.name.clone())
+            .or_default()
+            .push(symbol);
+    }
+
+    /// Every declaration with that name, from any file
+    pub fn get_all(&self, name: &str) -> &[Symbol] {
+        self.symbols
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.get_all(name).first()
+    }
+
+    pub fn get_kind(&self, name: &str, kind: SymbolKind) -> Option<&Symbol> {
+        self.get_all(name).iter().find(|e| e.kind == kind)
+    }
+
+    pub fn is_enum(&self, name: &str) -> bool {
+        self.get_kind(name, SymbolKind::Enum).is_some()
+    }
+
+    /// The declaration of `name` made in one of `files`
+    pub fn get_declared_in(&self, name: &str, files: &[PathBuf]) -> Option<&Symbol> {
+        self.get_all(name).iter().find(|e| files.contains(&e.path))
+    }
+
+    pub fn directives(&self, path: &Path) -> &[Directive] {
+        self.directives
+            .get(&normalize_path(path))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Names declared in `path` and the files it includes with `part`
+    fn library_declarations(&self, path: &Path, config: &PackageConfig) -> Vec<(String, PathBuf)> {
+        let mut names = Vec::new();
+        let parts = self
+            .directives(path)
+            .iter()
+            .filter(|e| e.kind == DirectiveKind::Part)
+            .filter_map(|e| config.resolve_uri(path, &e.uri));
+
+        for file in std::iter::once(path.to_owned()).chain(parts) {
+            for name in self.declarations.get(&file).into_iter().flatten() {
+                names.push((name.clone(), file.clone()));
+            }
+        }
+        names
+    }
+
+    /// Names a library makes available to its importers, following its `export`s
+    fn exported_names(
+        &self,
+        path: &Path,
+        config: &PackageConfig,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Vec<(String, PathBuf)> {
+        if !visited.insert(path.to_owned()) {
+            return Vec::new();
+        }
+
+        let mut names = self.library_declarations(path, config);
+        for export in self.directives(path) {
+            if export.kind != DirectiveKind::Export {
+                continue;
+            }
+            let Some(target) = config.resolve_uri(path, &export.uri) else {
+                continue;
+            };
+            names.extend(
+                self.exported_names(&target, config, visited)
+                    .into_iter()
+                    .filter(|e| export.exposes(&e.0)),
+            );
+        }
+        names
+    }
+
+    /// The project types visible from `path`
+    pub fn scope_for(&self, path: &Path, config: &PackageConfig) -> Scope {
+        let path = normalize_path(path);
+        let mut scope = Scope {
+            is_complete: true,
+            ..Default::default()
+        };
+
+        for (name, file) in self.library_declarations(&path, config) {
+            scope.add(name, file);
+        }
+
+        for import in self.directives(&path) {
+            if import.kind != DirectiveKind::Import {
+                continue;
+            }
+            let Some(target) = config.resolve_uri(&path, &import.uri) else {
+                // SDK libraries don't declare project types
+                scope.is_complete &= import.uri.starts_with("dart:");
+                continue;
+            };
+
+            for (name, file) in self.exported_names(&target, config, &mut HashSet::new()) {
+                if !import.exposes(&name) {
+                    continue;
+                }
+                match &import.prefix {
+                    Some(prefix) => scope.add(format!("{prefix}.{name}"), file),
+                    None => scope.add(name, file),
+                }
+            }
+        }
+
+        scope
+    }
+
+    /// Records the top-level declarations and directives of one file
+    pub fn index_file(&mut self, path: &Path, code: &str) {
+        let path = &normalize_path(path);
+        // Extension types are found without tree-sitter, which doesn't parse them
+        for symbol in index_extension_types(code, path) {
+            self.insert(symbol);
+        }
+        if !INDEXED_KEYWORDS.iter().any(|e| code.contains(e))
+            && !code.lines().any(|e| e.starts_with("const "))
+        {
+            return;
+        }
+
+        self.parsed_files += 1;
+        let mut parser = Parser::new();
+        parser
+            .set_language(&DART_TS)
+            .expect("Error loading Dart grammar");
+        let Some(tree) = parser.parse(code, None) else {
+            return;
+        };
+
+        let root = tree.root_node();
+        let mut cursor = root.walk();
+        for node in root.named_children(&mut cursor) {
+            if let Some(directive) = parse_directive(node, code) {
+                self.directives
+                    .entry(path.clone())
+                    .or_default()
+                    .push(directive);
+                continue;
+            }
+            if node.kind() == "static_final_declaration_list" {
+                for symbol in index_constants(node, code, path) {
+                    self.insert(symbol);
+                }
+                continue;
+            }
+
+            let symbol = match node.kind() {
+                "class_definition" => index_class(node, code, path),
+                "enum_declaration" => index_enum(node, code, path),
+                "mixin_declaration" => index_mixin(node, code, path),
+                "type_alias" => index_typedef(node, code, path),
+                _ => None,
+            };
+            if let Some(symbol) = symbol {
+                self.insert(symbol);
+            }
+        }
+    }
+}
+
+fn parse_directive(node: tree_sitter::Node, code: &str) -> Option<Directive> {
+    let (kind, node) = match node.kind() {
+        "import_or_export" => {
+            let inner = node.named_child(0)?;
+            match inner.kind() {
+                "library_import" => {
+                    let mut cursor = inner.walk();
+                    let specification = inner
+                        .named_children(&mut cursor)
+                        .find(|e| e.kind() == "import_specification")?;
+                    (DirectiveKind::Import, specification)
+                }
+                "library_export" => (DirectiveKind::Export, inner),
+                _ => return None,
+            }
+        }
+        "part_directive" => (DirectiveKind::Part, node),
+        _ => return None,
+    };
+
+    let mut directive = Directive {
+        kind,
+        uri: String::new(),
+        prefix: None,
+        show: Vec::new(),
+        hide: Vec::new(),
+    };
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "configurable_uri" => {
+                let uri = child.named_child(0)?;
+                directive.uri = unquote(&get_text(uri, code));
+            }
+            "uri" => directive.uri = unquote(&get_text(child, code)),
+            "identifier" => directive.prefix = Some(get_text(child, code)),
+            "combinator" => {
+                let mut combinator_cursor = child.walk();
+                let names = child
+                    .named_children(&mut combinator_cursor)
+                    .filter(|e| e.kind() == "identifier")
+                    .map(|e| get_text(e, code));
+                if child.child(0).is_some_and(|e| e.kind() == "hide") {
+                    directive.hide.extend(names);
+                } else {
+                    directive.show.extend(names);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (!directive.uri.is_empty()).then_some(directive)
+}
+
+fn unquote(literal: &str) -> String {
+    literal
+        .trim_start_matches('r')
+        .trim_matches(['\'', '"'])
+        .to_owned()
+}
+
+fn index_class(node: tree_sitter::Node, code: &str, path: &Path) -> Option<Symbol> {
+    let name = get_text(node.child_by_field_name("name")?, code);
+    let mut symbol = Symbol::new(name, SymbolKind::Class, path, get_span(node));
+
+    let annotations = parse_class_annotations(node, code);
+    symbol.is_freezed = annotations.iter().any(|e| e.is_freezed());
+    symbol.is_json_serializable = annotations.iter().any(|e| e.name == "JsonSerializable");
+    symbol.type_parameters = parse_type_parameters(node, code);
+
+    if let Some(superclass) = node.child_by_field_name("superclass") {
+        let mut cursor = superclass.walk();
+        for child in superclass.named_children(&mut cursor) {
+            if child.kind() == "mixins" {
+                symbol.supertypes.extend(parse_type_list(child, code));
+            } else if !child.kind().ends_with("comment") && symbol.supertypes.is_empty() {
+                symbol.supertypes.push(parse_type(child, code).0);
+            }
+        }
+    }
+    if let Some(interfaces) = node.child_by_field_name("interfaces") {
+        symbol.supertypes.extend(parse_type_list(interfaces, code));
+    }
+
+    if let Some(body) = node.child_by_field_name("body") {
+        let (has_from_json, has_to_json) = json_members(body, code);
+        symbol.has_from_json = has_from_json;
+        // freezed and json_serializable generate `toJson` next to `fromJson`
+        symbol.has_to_json =
+            has_to_json || (has_from_json && (symbol.is_freezed || symbol.is_json_serializable));
+    }
+
+    Some(symbol)
+}
+
+/// Whether a class or enum body declares `fromJson` and `toJson`
+fn json_members(body: tree_sitter::Node, code: &str) -> (bool, bool) {
+    let mut has_from_json = false;
+    let mut has_to_json = false;
+
+    let mut cursor = body.walk();
+    for member in body.named_children(&mut cursor) {
+        let signature = match member.kind() {
+            "declaration" | "method_signature" => member.named_child(0),
+            _ => None,
+        };
+        let Some(signature) = signature else {
+            continue;
+        };
+
+        let mut signature_cursor = signature.walk();
+        let names: Vec<String> = signature
+            .named_children(&mut signature_cursor)
+            .filter(|e| e.kind() == "identifier")
+            .map(|e| get_text(e, code))
+            .collect();
+
+        match signature.kind() {
+            "factory_constructor_signature" | "redirecting_factory_constructor_signature" => {
+                has_from_json |= names.get(1).is_some_and(|e| e == "fromJson");
+            }
+            "function_signature" => {
+                let name = signature
+                    .child_by_field_name("name")
+                    .map(|e| get_text(e, code));
+                has_to_json |= name.as_deref() == Some("toJson");
+                has_from_json |= name.as_deref() == Some("fromJson");
+            }
+            _ => {}
+        }
+    }
+
+    (has_from_json, has_to_json)
+}
+
+fn index_enum(node: tree_sitter::Node, code: &str, path: &Path) -> Option<Symbol> {
+    let name = get_text(node.child_by_field_name("name")?, code);
+    let mut symbol = Symbol::new(name, SymbolKind::Enum, path, get_span(node));
+
+    let body = node.child_by_field_name("body")?;
+    let mut cursor = body.walk();
+    for constant in body.named_children(&mut cursor) {
+        if constant.kind() != "enum_constant" {
+            continue;
+        }
+        let Some(constant_name) = constant.child_by_field_name("name") else {
+            continue;
+        };
+        let constant_name = get_text(constant_name, code);
+
+        let mut constant_cursor = constant.walk();
+        let json_value = constant
+            .named_children(&mut constant_cursor)
+            .filter(|e| e.kind() == "annotation")
+            .map(|e| parse_annotation(e, code))
+            .find(|e| e.name == "JsonValue")
+            .and_then(|e| e.arguments.first().cloned())
+            .unwrap_or_else(|| format!("'{constant_name}'"));
+
+        symbol.enum_values.push((constant_name, json_value));
+    }
+
+    let (has_from_json, has_to_json) = json_members(body, code);
+    symbol.has_from_json = has_from_json;
+    symbol.has_to_json = has_to_json;
+
+    Some(symbol)
+}
+
+fn index_mixin(node: tree_sitter::Node, code: &str, path: &Path) -> Option<Symbol> {
+    let mut cursor = node.walk();
+    let name = node
+        .named_children(&mut cursor)
+        .find(|e| e.kind() == "identifier")?;
+    let mut symbol = Symbol::new(
+        get_text(name, code),
+        SymbolKind::Mixin,
+        path,
+        get_span(node),
+    );
+    symbol.type_parameters = parse_type_parameters(node, code);
+
+    Some(symbol)
+}
+
+fn index_typedef(node: tree_sitter::Node, code: &str, path: &Path) -> Option<Symbol> {
+    // Both `typedef Name = Type;` and `typedef void Name();` name the alias with the
+    // last type identifier before the parameters or the `=`, only the first form can
+    // alias something else than a function
+    let mut cursor = node.walk();
+    let mut name = None;
+    let mut aliased_type = None;
+    let mut is_after_equals = false;
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "type_identifier" if is_after_equals => {
+                aliased_type = Some(parse_type(child, code).0);
+                break;
+            }
+            "type_identifier" => name = Some(child),
+            "=" => is_after_equals = true,
+            "formal_parameter_list" => break,
+            _ if is_after_equals && child.is_named() => break,
+            _ => {}
+        }
+    }
+
+    let mut symbol = Symbol::new(
+        get_text(name?, code),
+        SymbolKind::Typedef,
+        path,
+        get_span(node),
+    );
+    symbol.type_parameters = parse_type_parameters(node, code);
+    symbol.aliased_type = aliased_type;
+
+    Some(symbol)
+}
+
+/// `const epochConverter = EpochConverter();`, constants created another way don't
+/// name a class
+fn index_constants(node: tree_sitter::Node, code: &str, path: &Path) -> Vec<Symbol> {
+    let is_identifier = |e: char| e.is_alphanumeric() || matches!(e, '_' | '$' | '.');
+
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|e| e.kind() == "static_final_declaration")
+        .filter_map(|declaration| {
+            let text = get_text(declaration, code);
+            let (name, value) = text.split_once('=')?;
+            let value = value.trim();
+            let value = value.strip_prefix("const ").unwrap_or(value).trim_start();
+            let mut type_name = value[..value.find(['(', '<'])?].trim();
+            // `EpochConverter.utc()` calls a named constructor, `p.EpochConverter()` has a prefix
+            if let Some((class, constructor)) = type_name.rsplit_once('.')
+                && constructor.starts_with(char::is_lowercase)
+            {
+                type_name = class;
+            }
+            if type_name.is_empty() || !type_name.chars().all(is_identifier) {
+                return None;
+            }
+
+            let mut symbol = Symbol::new(
+                name.split_whitespace().last()?.to_owned(),
+                SymbolKind::Constant,
+                path,
+                get_span(declaration),
+            );
+            symbol.value_type = Some(DartType {
+                name: type_name.to_owned(),
+                ..Default::default()
+            });
+            Some(symbol)
+        })
+        .collect()
+}
+
+fn parse_type_parameters(node: tree_sitter::Node, code: &str) -> Vec<String> {
+    let mut cursor = node.walk();
+    let Some(type_parameters) = node
+        .named_children(&mut cursor)
+        .find(|e| e.kind() == "type_parameters")
+    else {
+        return Vec::new();
+    };
+
+    let mut parameter_cursor = type_parameters.walk();
+    type_parameters
+        .named_children(&mut parameter_cursor)
+        .filter_map(|parameter| {
+            let mut cursor = parameter.walk();
+            let name = parameter
+                .named_children(&mut cursor)
+                .find(|e| e.kind() == "type_identifier")?;
+            Some(get_text(name, code))
+        })
+        .collect()
+}
+
+/// The grammar predates extension types and turns them into ERROR nodes, so they're
+/// found by looking for `extension type Name(Type field)` where a declaration starts,
+/// after metadata and comments but outside of them and of strings
+fn index_extension_types(code: &str, path: &Path) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let mut i = 0;
+    let mut is_declaration_start = true;
+    // Where the annotations before the current declaration start
+    let mut metadata_start = None;
+
+    while let Some(c) = code[i..].chars().next() {
+        if let Some(end) = literal_end(code, i) {
+            // Comments don't end a declaration's metadata, strings aren't metadata
+            if !code[i..].starts_with('/') {
+                is_declaration_start = false;
+            }
+            i = end;
+            continue;
+        }
+
+        if is_declaration_start && c == '@' {
+            metadata_start.get_or_insert(i);
+            i = metadata_end(code, i);
+            continue;
+        }
+
+        if is_declaration_start
+            && code[i..].starts_with("extension type ")
+            && let Some((mut symbol, end_byte)) = parse_extension_type(code, i, path)
+        {
+            let start_byte = metadata_start.take().unwrap_or(i);
+            let start = &code[..start_byte];
+            let end = &code[..end_byte];
+            let column = |text: &str| text.len() - text.rfind('\n').map_or(0, |e| e + 1) + 1;
+            symbol.span = Span {
+                start_byte,
+                end_byte,
+                start: Position {
+                    line: start.matches('\n').count() + 1,
+                    column: column(start),
+                },
+                end: Position {
+                    line: end.matches('\n').count() + 1,
+                    column: column(end),
+                },
+            };
+            symbols.push(symbol);
+            i = end_byte;
+            continue;
+        }
+
+        if matches!(c, ';' | '}') {
+            is_declaration_start = true;
+            metadata_start = None;
+        } else if c == '\n' {
+            // Keeping the metadata of the lines before
+            is_declaration_start = true;
+        } else if !c.is_whitespace() {
+            is_declaration_start = false;
+            metadata_start = None;
+        }
+        i += c.len_utf8();
+    }
+
+    symbols
+}
+
+/// Where the annotation starting at `start`, `@JsonKey(name: 'id')` or `@p.Immutable<T>()`,
+/// ends
+fn metadata_end(code: &str, start: usize) -> usize {
+    let is_name = |e: char| e.is_alphanumeric() || matches!(e, '_' | '$' | '.');
+    let mut end = start + 1;
+    end += code[end..]
+        .find(|e| !is_name(e))
+        .unwrap_or(code.len() - end);
+    for (open, close) in [('<', '>'), ('(', ')')] {
+        if code[end..].starts_with(open)
+            && let Some(length) = matching_bracket(&code[end..], open, close)
+        {
+            end += length;
+        }
+    }
+    end
+}
+
+/// Byte ranges of the extension type declarations, which the grammar reports as errors
+pub(crate) fn extension_type_ranges(code: &str) -> Vec<std::ops::Range<usize>> {
+    index_extension_types(code, Path::new(""))
+        .into_iter()
+        .map(|e| e.span.start_byte..e.span.end_byte)
+        .collect()
+}
+
+fn parse_extension_type(code: &str, start_byte: usize, path: &Path) -> Option<(Symbol, usize)> {
+    let declaration = code[start_byte..].strip_prefix("extension type ")?;
+    let declaration = declaration.trim_start();
+    let declaration = declaration
+        .strip_prefix("const ")
+        .unwrap_or(declaration)
+        .trim_start();
+
+    let is_identifier = |e: char| e.is_alphanumeric() || e == '_' || e == '$';
+    let name_length = declaration.find(|e| !is_identifier(e))?;
+    let name = &declaration[..name_length];
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut symbol = Symbol::new(
+        name.to_owned(),
+        SymbolKind::ExtensionType,
+        path,
+        Span::default(),
+    );
+
+    let mut rest = declaration[name_length..].trim_start();
+    if rest.starts_with('<') {
+        let length = matching_bracket(rest, '<', '>')?;
+        symbol.type_parameters = split_top_level(&rest[1..length - 1])
+            .iter()
+            .filter_map(|e| e.split_whitespace().next())
+            .map(str::to_owned)
+            .collect();
+        rest = rest[length..].trim_start();
+    }
+
+    let mut constructor = name.to_owned();
+    if let Some(named) = rest.strip_prefix('.') {
+        let length = named.find(|e| !is_identifier(e))?;
+        constructor = format!("{name}.{}", &named[..length]);
+        rest = named[length..].trim_start();
+    }
+
+    if rest.starts_with('(') {
+        let length = matching_bracket(rest, '(', ')')?;
+        let field = rest[1..length - 1].trim();
+        // Annotations on the field don't change how it's stored
+        let field = field.rsplit_once(") ").map_or(field, |e| e.1).trim();
+        let (field_type, field_name) = field.rsplit_once(char::is_whitespace)?;
+        symbol.representation = Some(Representation {
+            constructor,
+            field: field_name.to_owned(),
+            dart_type: DartType::parse_raw(field_type),
+        });
+        rest = &rest[length..];
+    }
+
+    let body_start = rest.find(['{', ';'])?;
+    let mut end = code.len() - rest.len() + body_start + 1;
+    if rest[body_start..].starts_with('{') {
+        let length = matching_bracket(&rest[body_start..], '{', '}')?;
+        let members = member_text(&rest[body_start..body_start + length]);
+        symbol.has_from_json = declares_from_json(&members, name);
+        symbol.has_to_json = declares_to_json(&members);
+        end = code.len() - rest.len() + body_start + length;
+    }
+
+    Some((symbol, end))
+}
+
+/// Length of `text` up to the bracket closing its first one, brackets in strings and
+/// comments not counting
+fn matching_bracket(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        if let Some(end) = literal_end(text, i) {
+            i = end;
+            continue;
+        }
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i + c.len_utf8());
+            }
+        }
+        i += c.len_utf8();
+    }
+    None
+}
+
+/// Where the comment or string literal starting at `start` ends, `None` if there's none
+fn literal_end(text: &str, start: usize) -> Option<usize> {
+    let rest = &text[start..];
+    if rest.starts_with("//") {
+        return Some(rest.find('\n').map_or(text.len(), |e| start + e));
+    }
+    if rest.starts_with("/*") {
+        // Block comments nest in Dart
+        let mut depth = 0;
+        let mut i = start;
+        while let Some(c) = text[i..].chars().next() {
+            if text[i..].starts_with("/*") {
+                depth += 1;
+                i += 2;
+            } else if text[i..].starts_with("*/") {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(i);
+                }
+            } else {
+                i += c.len_utf8();
+            }
+        }
+        return Some(text.len());
+    }
+
+    let is_identifier = |e: char| e.is_alphanumeric() || e == '_' || e == '$';
+    let is_raw = (rest.starts_with("r'") || rest.starts_with("r\""))
+        && !text[..start].ends_with(is_identifier);
+    let quote_start = if is_raw { start + 1 } else { start };
+    let quote = ["'''", "\"\"\"", "'", "\""]
+        .into_iter()
+        .find(|e| text[quote_start..].starts_with(e))?;
+
+    let mut i = quote_start + quote.len();
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        if rest.starts_with(quote) {
+            return Some(i + quote.len());
+        }
+        if !is_raw && c == '\\' {
+            i += 1;
+            i += text[i..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+        if !is_raw && rest.starts_with("${") {
+            i += 1 + matching_bracket(&rest[1..], '{', '}')?;
+            continue;
+        }
+        // Unterminated, the line is all it can take
+        if quote.len() == 1 && c == '\n' {
+            return Some(i);
+        }
+        i += c.len_utf8();
+    }
+    Some(text.len())
+}
+
+/// The member declarations of a `{ ... }` body: comments, strings and whatever is inside
+/// brackets blanked out, so calls in parameters and function bodies are gone
+fn member_text(body: &str) -> String {
+    let inner = body
+        .strip_prefix('{')
+        .and_then(|e| e.strip_suffix('}'))
+        .unwrap_or(body);
+
+    let mut members = String::with_capacity(inner.len());
+    let mut depth = 0usize;
+    let mut i = 0;
+    while let Some(c) = inner[i..].chars().next() {
+        if let Some(end) = literal_end(inner, i) {
+            members.push(' ');
+            i = end;
+            continue;
+        }
+        match c {
+            '(' | '[' | '{' => {
+                if depth == 0 {
+                    members.push(c);
+                }
+                depth += 1;
+            }
+            ')' | ']' | '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    members.push(c);
+                }
+            }
+            _ if depth == 0 => members.push(c),
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    members
+}
+
+/// Whether `members` declares a `toJson()` method, not just calls one in an expression body
+fn declares_to_json(members: &str) -> bool {
+    members.match_indices("toJson").any(|(i, _)| {
+        let before = members[..i].trim_end();
+        let after = members[i + "toJson".len()..].trim_start();
+        let is_declared = before.ends_with(|e: char| e.is_alphanumeric() || e == '_' || e == '?')
+            || (before.ends_with('>') && !before.ends_with("=>"));
+        is_declared && after.starts_with('(')
+    })
+}
+
+/// Whether `members` declares a `Name.fromJson` constructor
+fn declares_from_json(members: &str, name: &str) -> bool {
+    let constructor = format!("{name}.fromJson");
+    members.match_indices(&constructor).any(|(i, _)| {
+        let before = members[..i].trim_end();
+        let after = members[i + constructor.len()..].trim_start();
+        let is_declared = before.is_empty()
+            || before.ends_with("factory")
+            || before.ends_with("const")
+            || before.ends_with([';', '{', '}', ')']);
+        is_declared && after.starts_with('(')
+    })
+}
@@ -1,4 +1,3 @@
-
 #[test]
 fn test_pasing_types() {
     //     let code = r#"
@@ -11,3 +10,1808 @@ fn test_pasing_types() {
     // }
     //     "#;
 }
+
+/// Generates `code` as `/project/lib/model.dart` with only its own types indexed
+fn generate_model(
+    code: &str,
+    options: &faster_freezed::json_serialization::GeneratorOptions,
+) -> faster_freezed::GeneratedFiles {
+    use faster_freezed::json_serialization::GenerationContext;
+    use faster_freezed::package_config::PackageConfig;
+    use faster_freezed::project_index::ProjectIndex;
+    use std::path::Path;
+
+    let path = Path::new("/project/lib/model.dart");
+    let mut index = ProjectIndex::new();
+    index.index_file(path, code);
+    let config = PackageConfig::default();
+    let scope = index.scope_for(path, &config);
+    let context = GenerationContext::new(&index, &scope, &config, options);
+    faster_freezed::generate_files(code, "model.dart", &context)
+}
+
+/// The content of a file that's expected to be written
+fn written(file: &faster_freezed::GeneratedFile) -> &str {
+    match file {
+        faster_freezed::GeneratedFile::Write(content) => content,
+        _ => panic!("expected a written file, got {file:?}"),
+    }
+}
+
+/// The `.freezed.dart` and `.g.dart` contents of `code`, empty when one isn't written
+fn generate_code(code: &str) -> (String, String) {
+    generate_code_with(code, &Default::default())
+}
+
+fn generate_code_with(
+    code: &str,
+    options: &faster_freezed::json_serialization::GeneratorOptions,
+) -> (String, String) {
+    use faster_freezed::GeneratedFile;
+
+    let generated = generate_model(code, options);
+    let content = |file: GeneratedFile| match file {
+        GeneratedFile::Write(content) => content,
+        GeneratedFile::Keep | GeneratedFile::Remove => String::new(),
+    };
+    (content(generated.freezed), content(generated.json))
+}
+
+#[test]
+fn test_case_classes_implement_class_supertypes() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@freezed
+abstract class Foo extends Base with _$Foo, A, B implements Comparable<Foo> {
+  @With<B>()
+  const factory Foo(int value) = _Foo;
+}
+"#;
+    let parsed = parse_dart_code(code);
+    let class = &parsed.classes[0];
+    assert_eq!(class.superclass.as_ref().unwrap().as_raw(), "Base");
+    let mixins: Vec<_> = class.mixins.iter().map(|e| e.as_raw()).collect();
+    assert_eq!(mixins, ["_$Foo", "A", "B"]);
+    let interfaces: Vec<_> = class.interfaces.iter().map(|e| e.as_raw()).collect();
+    assert_eq!(interfaces, ["Comparable<Foo>"]);
+    let supertypes: Vec<_> = class.supertypes().iter().map(|e| e.as_raw()).collect();
+    assert_eq!(supertypes, ["Base", "A", "B", "Comparable<Foo>"]);
+
+    let (freezed, _) = generate_code(code);
+    assert!(freezed.contains("class _Foo with B implements Foo, Base, A, Comparable<Foo> {"));
+}
+
+#[test]
+fn test_classic_class_and_late_fields() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@freezed
+class Person with _$Person {
+  const Person({required this.name, this.age = 0});
+
+  @override
+  final String name;
+  @override
+  final int age;
+}
+
+@freezed
+class Session with _$Session {
+  Session(this.token);
+
+  final String token;
+  late final DateTime startedAt;
+  late List<String>? scopes;
+  late var label = '';
+}
+"#;
+    let parsed = parse_dart_code(code);
+    assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+
+    let person = &parsed.classes[0];
+    assert!(person.redirecting_constructors.is_empty());
+    let constructor = person.generative_constructor.as_ref().unwrap();
+    let parameters: Vec<_> = constructor
+        .parameters
+        .get_all_params()
+        .iter()
+        .map(|e| (e.name.clone(), e.dart_type.as_raw()))
+        .collect();
+    assert_eq!(
+        parameters,
+        [
+            ("name".to_owned(), "String".to_owned()),
+            ("age".to_owned(), "int".to_owned())
+        ]
+    );
+
+    let session = &parsed.classes[1];
+    let fields: Vec<_> = session
+        .fields
+        .iter()
+        .map(|e| (e.name.as_str(), e.dart_type.as_raw()))
+        .collect();
+    assert_eq!(fields[0], ("token", "String".to_owned()));
+    assert_eq!(fields[1], ("startedAt", "DateTime".to_owned()));
+    assert_eq!(fields[2], ("scopes", "List<String>?".to_owned()));
+    assert_eq!(fields[3].0, "label");
+}
+
+#[test]
+fn test_from_json_forwarding() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@freezed
+abstract class Custom with _$Custom {
+  const factory Custom(int a) = _Custom;
+
+  factory Custom.empty() => const Custom(0);
+
+  factory Custom.fromJson(Map<String, dynamic> json) =>
+      // the generated function, renamed
+      _$CustomFromJsonCustom(json);
+}
+
+@freezed
+abstract class Block with _$Block {
+  const factory Block(int a) = _Block;
+
+  factory Block.fromJson(Map<String, dynamic> json) {
+    return _$BlockFromJson(json);
+  }
+}
+
+@freezed
+abstract class Wrapped with _$Wrapped {
+  const factory Wrapped(int a) = _Wrapped;
+
+  factory Wrapped.fromJson(Map<String, dynamic> json) => migrate(json);
+}
+"#;
+    let parsed = parse_dart_code(code);
+    let from_json: Vec<_> = parsed
+        .classes
+        .iter()
+        .map(|e| e.from_json_function())
+        .collect();
+    assert_eq!(
+        from_json,
+        [
+            Some("_$CustomFromJsonCustom"),
+            Some("_$BlockFromJson"),
+            // Not a generated function, so the default name
+            Some("_$WrappedFromJson"),
+        ]
+    );
+
+    // `Custom.empty` is neither a case nor the JSON constructor
+    assert_eq!(parsed.classes[0].redirecting_constructors.len(), 1);
+}
+
+#[test]
+fn test_initializing_formals_dont_repeat_field_annotations() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@freezed
+class User with _$User {
+  User(@JsonKey(name: 'user_id') this.id, this.name);
+
+  @JsonKey(name: 'user_id')
+  final int id;
+  @JsonKey(includeIfNull: false)
+  final String? name;
+}
+"#;
+    let parsed = parse_dart_code(code);
+    let constructor = parsed.classes[0].generative_constructor.as_ref().unwrap();
+    let parameters = &constructor.parameters.positional_parameters;
+
+    assert_eq!(parameters[0].annotations.len(), 1);
+    assert_eq!(parameters[1].annotations.len(), 1);
+    assert_eq!(parameters[1].dart_type.as_raw(), "String?");
+}
+
+#[test]
+fn test_default_without_value_is_reported() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@freezed
+abstract class Broken with _$Broken {
+  const factory Broken({@Default() int count}) = _Broken;
+}
+
+@freezed
+abstract class Fine with _$Fine {
+  const factory Fine({@Default(1) int count}) = _Fine;
+}
+"#;
+    let parsed = parse_dart_code(code);
+
+    assert!(parsed.has_errors());
+    assert_eq!(parsed.diagnostics.len(), 1, "{:?}", parsed.diagnostics);
+    let diagnostic = &parsed.diagnostics[0];
+    assert_eq!(diagnostic.code, "invalid-default");
+    assert!(diagnostic.message.contains("`count` in `Broken`"));
+
+    let names: Vec<_> = parsed.classes.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["Fine"]);
+}
+
+#[test]
+fn test_syntax_errors_skip_the_file() {
+    use faster_freezed::GeneratedFile;
+    use faster_freezed::json_serialization::GeneratorOptions;
+    use faster_freezed::parser::{SYNTAX_ERROR, parse_dart_code};
+
+    // Mid-edit, a parameter without its type's closing `>`
+    let code = r#"
+@freezed
+abstract class Draft with _$Draft {
+  const factory Draft({required List<String tags}) = _Draft;
+}
+"#;
+    let parsed = parse_dart_code(code);
+    assert!(parsed.has_syntax_errors());
+    let errors: Vec<_> = parsed
+        .diagnostics
+        .iter()
+        .filter(|e| e.code == SYNTAX_ERROR)
+        .collect();
+    assert!(!errors.is_empty());
+    assert!(errors.iter().all(|e| e.is_error()));
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.message.contains("freezed class `Draft`"))
+    );
+    assert!(parsed.classes.is_empty());
+
+    // Nothing is written, the previous output stays until the file parses again
+    let generated = generate_model(code, &GeneratorOptions::default());
+    assert!(generated.is_skipped);
+    assert_eq!(generated.freezed, GeneratedFile::Keep);
+    assert_eq!(generated.json, GeneratedFile::Keep);
+}
+
+#[test]
+fn test_documentation_is_carried_over() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@freezed
+abstract class Book with _$Book {
+  /// A printed book
+  const factory Book({
+    /// The title on the cover
+    ///   indented
+    required String title,
+    // a plain comment isn't documentation
+    required int pages,
+  }) = _Book;
+}
+"#;
+    let parsed = parse_dart_code(code);
+    let constructor = &parsed.classes[0].redirecting_constructors[0];
+    assert_eq!(
+        constructor.documentation.as_deref(),
+        Some("/// A printed book")
+    );
+    let parameters = &constructor.parameters.named_parameters;
+    let lines: Vec<_> = parameters[0]
+        .documentation
+        .iter()
+        .flat_map(|e| e.lines().map(str::trim))
+        .collect();
+    assert_eq!(lines, ["/// The title on the cover", "///   indented"]);
+    assert_eq!(parameters[1].documentation, None);
+
+    let (freezed, _) = generate_code(code);
+    // The mixin getter, the case class field and the copyWith parameters
+    assert!(freezed.matches("/// The title on the cover\n").count() >= 3);
+    assert!(
+        freezed.contains("  /// The title on the cover\n  ///   indented\n  String get title;")
+    );
+    assert!(freezed.contains("/// A printed book\nclass _Book"));
+    assert!(!freezed.contains("a plain comment"));
+}
+
+#[test]
+fn test_documentation_of_factories_and_dangling_comments() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@freezed
+sealed class Shape with _$Shape {
+  /// A circle
+  ///   with a radius
+  const factory Shape.circle({
+    required double radius,
+    /// documents nothing
+  }) = Circle;
+
+  /// A square
+  @Implements<Comparable<Shape>>()
+  const factory Shape.square({required double side}) = Square;
+}
+"#;
+    let parsed = parse_dart_code(code);
+    assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+    let [circle, square] = parsed.classes[0].redirecting_constructors.as_slice() else {
+        panic!("expected two cases");
+    };
+    let lines: Vec<_> = circle
+        .documentation
+        .iter()
+        .flat_map(|e| e.lines().map(str::trim))
+        .collect();
+    assert_eq!(lines, ["/// A circle", "///   with a radius"]);
+    // Annotations between the comment and the factory don't detach it
+    assert_eq!(square.documentation.as_deref(), Some("/// A square"));
+
+    // The trailing comment isn't a parameter of its own
+    let parameters = &circle.parameters.named_parameters;
+    assert_eq!(parameters.len(), 1);
+    assert_eq!(parameters[0].name, "radius");
+    assert_eq!(parameters[0].documentation, None);
+
+    let (freezed, _) = generate_code(code);
+    assert!(freezed.contains("/// A circle\n///   with a radius\nclass Circle"));
+    assert!(freezed.contains("/// A square\nclass Square"));
+    assert!(!freezed.contains("documents nothing"));
+}
+
+#[test]
+fn test_parameter_annotations_on_generated_fields() {
+    let code = r#"
+@freezed
+sealed class Event with _$Event {
+  const factory Event.created({
+    @JsonKey(name: 'event_id') @Deprecated('use uuid') required int id,
+    @Default(0) int retries,
+  }) = Created;
+  const factory Event.deleted({required int id, required String reason}) = Deleted;
+}
+"#;
+    let (freezed, _) = generate_code(code);
+
+    // `id` is shared through the mixin, the rest only belongs to its case
+    assert!(freezed.contains(
+        "  @JsonKey(name: 'event_id')\n  @Deprecated('use uuid')\n  @override\n  final int id;"
+    ));
+    assert!(freezed.contains("\n  final int retries;"));
+    assert!(!freezed.contains("@override\n  final int retries;"));
+    assert!(freezed.contains("\n  final String reason;"));
+    // freezed's own annotations aren't repeated
+    assert!(!freezed.contains("  @Default(0)\n"));
+}
+
+#[test]
+fn test_union_field_least_upper_bound() {
+    use faster_freezed::dart_types::DartType;
+
+    let simple = |name: &str, nullable: bool| DartType {
+        name: name.to_string(),
+        nullable,
+        type_arguments: Vec::new(),
+    };
+    let list_of = |inner: DartType| DartType {
+        name: "List".to_string(),
+        nullable: false,
+        type_arguments: vec![inner],
+    };
+
+    assert_eq!(
+        simple("int", false)
+            .least_upper_bound(&simple("int", true))
+            .as_raw(),
+        "int?"
+    );
+    assert_eq!(
+        simple("int", false)
+            .least_upper_bound(&simple("double", false))
+            .as_raw(),
+        "num"
+    );
+    assert_eq!(
+        list_of(simple("int", false))
+            .least_upper_bound(&list_of(simple("String", false)))
+            .as_raw(),
+        "List<Object>"
+    );
+    assert_eq!(
+        simple("Cat", false)
+            .least_upper_bound(&simple("Dog", true))
+            .as_raw(),
+        "Object?"
+    );
+}
+
+#[test]
+fn test_union_field_least_upper_bound_in_the_class_hierarchy() {
+    use faster_freezed::dart_types::DartType;
+    use faster_freezed::json_serialization::GenerationContext;
+    use faster_freezed::package_config::PackageConfig;
+    use faster_freezed::project_index::ProjectIndex;
+    use std::path::Path;
+
+    let path = Path::new("/project/lib/animals.dart");
+    let mut index = ProjectIndex::new();
+    index.index_file(
+        path,
+        r#"
+abstract class Animal {}
+abstract class Pet {}
+class Cat extends Animal {}
+class Kitten extends Cat {}
+class Dog extends Animal implements Pet {}
+class Robot implements Pet {}
+class Puppy extends Animal implements Pet {}
+class Box<T> {}
+class CatBox extends Box<Cat> {}
+class DogBox extends Box<Dog> {}
+"#,
+    );
+    let config = PackageConfig::default();
+    let scope = index.scope_for(path, &config);
+    let options = Default::default();
+    let context = GenerationContext::new(&index, &scope, &config, &options);
+
+    let bound = |a: &str, b: &str| {
+        let parse = DartType::parse_raw;
+        context.least_upper_bound(&parse(a), &parse(b)).as_raw()
+    };
+    assert_eq!(bound("Cat", "Dog"), "Animal");
+    assert_eq!(bound("Kitten", "Dog?"), "Animal?");
+    assert_eq!(bound("Kitten?", "Cat"), "Cat?");
+    assert_eq!(bound("Dog", "Robot"), "Pet");
+    assert_eq!(bound("CatBox", "DogBox"), "Box<Animal>");
+    assert_eq!(bound("List<Cat>", "List<Dog>"), "List<Animal>");
+    // `Animal` and `Pet` are as deep, so neither is picked
+    assert_eq!(bound("Dog", "Puppy"), "Object");
+    // Classes outside the project still meet at `Object`
+    assert_eq!(bound("Cat", "Unknown"), "Object");
+    assert_eq!(bound("int", "double"), "num");
+}
+
+#[test]
+fn test_union_fields_with_different_types_arent_copied() {
+    let code = r#"
+abstract class Animal {}
+class Cat extends Animal {}
+class Dog extends Animal {}
+
+@freezed
+sealed class Adoption with _$Adoption {
+  const factory Adoption.cat({required String owner, required Cat pet}) = CatAdoption;
+  const factory Adoption.dog({required String owner, required Dog pet}) = DogAdoption;
+}
+"#;
+    let (freezed, _) = generate_code(code);
+
+    assert!(freezed.contains("  String get owner;"));
+    assert!(freezed.contains("  Animal get pet;"));
+
+    let copy_with = freezed
+        .split("abstract mixin class $AdoptionCopyWith<$Res>")
+        .nth(1)
+        .and_then(|e| e.split("\n}").next())
+        .unwrap();
+    assert!(copy_with.contains("String owner"));
+    assert!(!copy_with.contains("pet"));
+    // Each case still copies its own `pet`
+    assert!(freezed.contains("Cat pet"));
+    assert!(freezed.contains("Dog pet"));
+}
+
+#[test]
+fn test_enum_json() {
+    let code = r#"
+enum Status {
+  active,
+  @JsonValue('gone')
+  deleted,
+}
+
+@freezed
+abstract class Account with _$Account {
+  const factory Account({
+    required Status status,
+    Status? previous,
+    required List<Status> history,
+  }) = _Account;
+
+  factory Account.fromJson(Map<String, dynamic> json) => _$AccountFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    assert!(json.contains("$enumDecode(_$StatusEnumMap, json['status'])"));
+    assert!(json.contains("$enumDecodeNullable(_$StatusEnumMap, json['previous'])"));
+    assert!(json.contains("_$StatusEnumMap[instance.status]!"));
+    assert_eq!(json.matches("const _$StatusEnumMap = {").count(), 1);
+    assert!(json.contains("  Status.active: 'active',"));
+    assert!(json.contains("  Status.deleted: 'gone',"));
+}
+
+#[test]
+fn test_files_without_declarations_arent_parsed() {
+    use faster_freezed::project_index::ProjectIndex;
+    use std::path::Path;
+
+    let mut index = ProjectIndex::new();
+    index.index_file(
+        Path::new("/project/lib/main.dart"),
+        "import 'app.dart';\n\nvoid main() => runApp(const App());\n",
+    );
+    index.index_file(
+        Path::new("/project/lib/ids.dart"),
+        "extension type const UserId(int value) {}\n",
+    );
+    assert_eq!(index.parsed_files(), 0);
+    assert_eq!(index.len(), 1);
+}
+
+#[test]
+fn test_package_config_resolves_package_uris() {
+    use faster_freezed::package_config::PackageConfig;
+    use std::path::Path;
+
+    let config = PackageConfig::parse(
+        r#"{
+  "configVersion": 2,
+  "packages": [
+    {"name": "app", "rootUri": "../", "packageUri": "lib/", "languageVersion": "3.4"},
+    {"name": "money", "rootUri": "file:///pub/money%20kit", "packageUri": "lib/"},
+    {"name": "emoji", "rootUri": "file:///pub/\uD83D\uDE00\u00e9", "packageUri": "lib/"},
+    {"name": "tools", "rootUri": "../packages/tools/", "packageUri": "src/../lib/"},
+    {"name": "root", "rootUri": "file:///pub/root"}
+  ],
+  "generator": "pub"
+}"#,
+        Path::new("/project/.dart_tool/package_config.json"),
+    )
+    .unwrap();
+
+    let from = Path::new("/project/lib/models/user.dart");
+    assert_eq!(
+        config.resolve_uri(from, "package:app/models/address.dart"),
+        Some("/project/lib/models/address.dart".into())
+    );
+    assert_eq!(
+        config.resolve_uri(from, "package:money/money.dart"),
+        Some("/pub/money kit/lib/money.dart".into())
+    );
+    assert_eq!(
+        config.resolve_uri(from, "../shared/id.dart"),
+        Some("/project/lib/shared/id.dart".into())
+    );
+    assert_eq!(
+        config.resolve_uri(from, "package:emoji/emoji.dart"),
+        Some("/pub/\u{1F600}\u{e9}/lib/emoji.dart".into())
+    );
+    // Relative to the `.dart_tool` directory, and `packageUri` to `rootUri`
+    assert_eq!(
+        config.resolve_uri(from, "package:tools/cli.dart"),
+        Some("/project/packages/tools/lib/cli.dart".into())
+    );
+    assert_eq!(
+        config.resolve_uri(from, "package:root/root.dart"),
+        Some("/pub/root/root.dart".into())
+    );
+    assert_eq!(config.resolve_uri(from, "dart:core"), None);
+    assert_eq!(config.resolve_uri(from, "package:missing/a.dart"), None);
+}
+
+#[test]
+fn test_malformed_package_configs() {
+    use faster_freezed::package_config::PackageConfig;
+    use std::path::Path;
+
+    let path = Path::new("/project/.dart_tool/package_config.json");
+    for malformed in [
+        "",
+        "{",
+        "[]",
+        r#"{"packages": {}}"#,
+        r#"{"packages": [{"name": "app", "rootUri": "../"}],}"#,
+        r#"{"packages": [{"name": "app", "rootUri": "file:///pub/\uD83D"}]}"#,
+        r#"{"packages": []} trailing"#,
+    ] {
+        assert!(
+            PackageConfig::parse(malformed, path).is_none(),
+            "{malformed:?} should be rejected"
+        );
+    }
+
+    // Entries without a name or a root are skipped, not the whole config
+    let config = PackageConfig::parse(
+        r#"{"packages": [{"name": "app"}, {"rootUri": "../"}, {"name": 1, "rootUri": "../"}]}"#,
+        path,
+    )
+    .unwrap();
+    assert_eq!(config.package_directory("app"), None);
+}
+
+#[test]
+fn test_prefixed_imports_and_combinators() {
+    use faster_freezed::json_serialization::{GenerationContext, GeneratorOptions};
+    use faster_freezed::package_config::PackageConfig;
+    use faster_freezed::project_index::ProjectIndex;
+    use std::path::{Path, PathBuf};
+
+    let mut index = ProjectIndex::new();
+    index.index_file(
+        Path::new("/project/lib/a.dart"),
+        "enum Status { active, deleted }\n",
+    );
+    index.index_file(
+        Path::new("/project/lib/levels.dart"),
+        "enum Level { low, high }\nenum Tier { free, paid }\n",
+    );
+    index.index_file(
+        Path::new("/project/lib/colors.dart"),
+        "enum Color { red, blue }\nenum Shade { light, dark }\n",
+    );
+    let path = Path::new("/project/lib/model.dart");
+    let code = r#"
+import 'a.dart' as a;
+import 'levels.dart' show Level;
+import 'colors.dart' hide Shade;
+
+@freezed
+abstract class Settings with _$Settings {
+  const factory Settings({
+    required a.Status status,
+    required Level level,
+    required Color color,
+  }) = _Settings;
+
+  factory Settings.fromJson(Map<String, dynamic> json) => _$SettingsFromJson(json);
+}
+"#;
+    index.index_file(path, code);
+
+    let config = PackageConfig::default();
+    let scope = index.scope_for(path, &config);
+    let declared = |name: &str| scope.declaring_files(name).to_vec();
+    assert_eq!(declared("a.Status"), [PathBuf::from("/project/lib/a.dart")]);
+    assert!(declared("Status").is_empty());
+    assert_eq!(
+        declared("Level"),
+        [PathBuf::from("/project/lib/levels.dart")]
+    );
+    assert!(declared("Tier").is_empty());
+    assert_eq!(
+        declared("Color"),
+        [PathBuf::from("/project/lib/colors.dart")]
+    );
+    assert!(declared("Shade").is_empty());
+
+    let options = GeneratorOptions::default();
+    let context = GenerationContext::new(&index, &scope, &config, &options);
+    let generated = faster_freezed::generate_files(code, "model.dart", &context);
+    let json = written(&generated.json);
+
+    assert!(json.contains("$enumDecode(_$StatusEnumMap, json['status'])"));
+    assert!(json.contains("  a.Status.active: 'active',"));
+    assert!(json.contains("$enumDecode(_$LevelEnumMap, json['level'])"));
+    assert!(json.contains("$enumDecode(_$ColorEnumMap, json['color'])"));
+    assert!(json.contains("  Color.blue: 'blue',"));
+}
+
+#[test]
+fn test_typedefs_expand_in_their_own_scope() {
+    use faster_freezed::dart_types::DartType;
+    use faster_freezed::json_serialization::{GenerationContext, GeneratorOptions};
+    use faster_freezed::package_config::PackageConfig;
+    use faster_freezed::project_index::ProjectIndex;
+    use std::path::Path;
+
+    // Two unrelated `Id`s, `types.dart` only sees the first
+    let mut index = ProjectIndex::new();
+    index.index_file(Path::new("/project/lib/b/ids.dart"), "typedef Id = String;");
+    index.index_file(Path::new("/project/lib/a/ids.dart"), "typedef Id = int;");
+    index.index_file(
+        Path::new("/project/lib/types.dart"),
+        "import 'a/ids.dart';\n\ntypedef Ids = List<Id>;\ntypedef Json<T> = Map<String, T>;\n",
+    );
+    let path = Path::new("/project/lib/model.dart");
+    index.index_file(
+        path,
+        "import 'types.dart';\n\ntypedef Id = double;\n\nclass Model {}\n",
+    );
+
+    let config = PackageConfig::default();
+    let scope = index.scope_for(path, &config);
+    let options = GeneratorOptions::default();
+    let context = GenerationContext::new(&index, &scope, &config, &options);
+    let expand = |name: &str, type_arguments: Vec<DartType>| {
+        let dart_type = DartType {
+            name: name.to_owned(),
+            nullable: false,
+            type_arguments,
+        };
+        context.expand_type(&dart_type).as_raw()
+    };
+    let named = |name: &str| DartType {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+
+    assert_eq!(expand("Ids", vec![]), "List<int>");
+    assert_eq!(expand("Id", vec![]), "double");
+    // The arguments are this file's types, the body the typedef's
+    assert_eq!(expand("Json", vec![named("Id")]), "Map<String, double>");
+    assert_eq!(expand("Json", vec![named("Ids")]), "Map<String, List<int>>");
+}
+
+#[test]
+fn test_typedef_fields_keep_their_alias() {
+    let code = r#"
+typedef UserId = String;
+typedef Pair<T> = Map<String, T>;
+
+@freezed
+abstract class Account with _$Account {
+  const factory Account({
+    required UserId id,
+    required Pair<int> scores,
+    Pair<UserId>? friends,
+  }) = _Account;
+
+  factory Account.fromJson(Map<String, dynamic> json) => _$AccountFromJson(json);
+}
+"#;
+    let (freezed, json) = generate_code(code);
+
+    // Decoded as what the alias stands for
+    assert!(json.contains("((json['id']) as String)"));
+    assert!(!json.contains("UserId.fromJson"));
+    assert!(!json.contains("Pair"));
+    assert!(json.contains(
+        "(json['scores'] as Map<String, dynamic>).map(\n    (k, e) => MapEntry(k, (e as num).toInt()))"
+    ));
+    assert!(json.contains(
+        "(json['friends'] as Map<String, dynamic>?)?.map(\n    (k, e) => MapEntry(k, ((e) as String)))"
+    ));
+
+    // Declared with the alias as written
+    assert!(freezed.contains("  UserId get id;"));
+    assert!(freezed.contains("  Pair<int> get scores;"));
+    assert!(freezed.contains("  Pair<UserId>? get friends;"));
+    assert!(freezed.contains("  final UserId id;"));
+    assert!(freezed.contains("  final Pair<int> scores;"));
+    assert!(freezed.contains("    UserId id,"));
+    assert!(freezed.contains("    Pair<int> scores,"));
+    assert!(freezed.contains("    Pair<UserId>? friends,"));
+    assert!(!freezed.contains("Map<String, int>"));
+}
+
+#[test]
+fn test_parse_raw_type() {
+    use faster_freezed::dart_types::DartType;
+
+    let map = DartType::parse_raw("Map<String, List<int?>>?");
+    assert_eq!(map.name, "Map");
+    assert!(map.nullable);
+    assert_eq!(map.type_arguments.len(), 2);
+    assert_eq!(map.type_arguments[1].as_raw(), "List<int?>");
+
+    let function = DartType::parse_raw("void Function(int, String)");
+    assert_eq!(function.name, "void Function(int, String)");
+    assert!(function.type_arguments.is_empty());
+}
+
+const EXTENSION_TYPES: &str = r#"
+// extension type Commented(int value) {}
+const template = '''
+extension type Quoted(int value) {}
+''';
+
+extension type const UserId._(int value) {
+  // other.toJson() in a comment isn't a declaration
+  static const closing = '}';
+  factory UserId.fromJson(Object json) => UserId._(json as int);
+  int toJson() => value;
+}
+
+extension type Wrapper(Other value) {
+  Object? get raw => value.toJson();
+  static Wrapper make() => Wrapper.fromJson(1);
+}
+
+@freezed
+abstract class Account with _$Account {
+  const factory Account({required UserId id}) = _Account;
+}
+"#;
+
+#[test]
+fn test_extension_types_are_indexed_past_strings_and_comments() {
+    use faster_freezed::project_index::{ProjectIndex, SymbolKind};
+    use std::path::Path;
+
+    let mut index = ProjectIndex::new();
+    index.index_file(Path::new("/project/lib/ids.dart"), EXTENSION_TYPES);
+
+    assert!(index.get("Commented").is_none());
+    assert!(index.get("Quoted").is_none());
+
+    let user_id = index.get_kind("UserId", SymbolKind::ExtensionType).unwrap();
+    assert!(user_id.has_from_json);
+    assert!(user_id.has_to_json);
+    assert_eq!(
+        user_id.representation.as_ref().unwrap().constructor,
+        "UserId._"
+    );
+
+    // Calling another type's methods doesn't declare them
+    let wrapper = index
+        .get_kind("Wrapper", SymbolKind::ExtensionType)
+        .unwrap();
+    assert!(!wrapper.has_from_json);
+    assert!(!wrapper.has_to_json);
+}
+
+#[test]
+fn test_extension_types_are_not_syntax_errors() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let result = parse_dart_code(EXTENSION_TYPES);
+    assert!(!result.has_syntax_errors(), "{:?}", result.diagnostics);
+    assert_eq!(result.classes.len(), 1);
+    assert_eq!(result.classes[0].name, "Account");
+}
+
+#[test]
+fn test_extension_types_after_metadata_and_comments() {
+    use faster_freezed::project_index::{ProjectIndex, SymbolKind};
+    use std::path::Path;
+
+    let code = r#"@immutable extension type const UserId(int value) {}
+/* legacy */ extension type OrderId._(String value) {}
+@JsonKey(name: 'x') /// docs
+@p.Sealed<int>() extension type Price(num value) {}
+var weight = 1; extension type Weight(double value) {}
+final label = 'extension type Quoted(int value) {}';
+final other = label; /* extension type Commented(int value) {} */
+"#;
+    let mut index = ProjectIndex::new();
+    index.index_file(Path::new("/project/lib/ids.dart"), code);
+
+    for name in ["UserId", "OrderId", "Price", "Weight"] {
+        assert!(
+            index.get_kind(name, SymbolKind::ExtensionType).is_some(),
+            "{name} isn't indexed"
+        );
+    }
+    assert!(index.get("Quoted").is_none());
+    assert!(index.get("Commented").is_none());
+
+    // The metadata belongs to the declaration, comments don't
+    let span = |name: &str| {
+        let span = index.get(name).unwrap().span;
+        (span.start_byte, span.start.line, span.start.column)
+    };
+    assert_eq!(span("UserId"), (0, 1, 1));
+    assert_eq!(span("OrderId"), (66, 2, 14));
+    assert_eq!(span("Price"), (108, 3, 1));
+}
+
+#[test]
+fn test_extension_type_fields() {
+    let code = r#"
+extension type const UserId._(String value) {
+  factory UserId.fromJson(Object? json) => UserId._(json as String);
+  String toJson() => value;
+}
+
+extension type Score(int points) {}
+
+extension type Tags(List<String> names) {}
+
+@freezed
+abstract class Profile with _$Profile {
+  const factory Profile({
+    required UserId id,
+    UserId? referrer,
+    required Score score,
+    required Tags tags,
+  }) = _Profile;
+
+  factory Profile.fromJson(Map<String, dynamic> json) => _$ProfileFromJson(json);
+}
+"#;
+    let (freezed, json) = generate_code(code);
+
+    // Their own `fromJson` takes the value as it is
+    assert!(json.contains("UserId.fromJson(json['id'])"));
+    assert!(json.contains("(json['referrer'] == null ? null : UserId.fromJson(json['referrer']))"));
+    assert!(!json.contains("UserId.fromJson(json['id'] as"));
+    assert!(json.contains("'id': instance.id.toJson(),"));
+    assert!(json.contains("'referrer': instance.referrer?.toJson(),"));
+
+    // Without one, the representation is decoded and wrapped
+    assert!(json.contains("Score((json['score'] as num).toInt())"));
+    assert!(json.contains("Tags((json['tags'] as List<dynamic>).map("));
+    assert!(!json.contains("Score.fromJson"));
+    assert!(!json.contains("Tags.fromJson"));
+    assert!(json.contains("'score': instance.score.points,"));
+    assert!(json.contains("'tags': instance.tags.names,"));
+
+    // Compared as what they wrap
+    assert!(freezed.contains("other.id == id"));
+    assert!(!freezed.contains("identical(other.id, id)"));
+    assert!(freezed.contains("const DeepCollectionEquality().equals(other.tags, tags)"));
+    assert!(freezed.contains("const DeepCollectionEquality().hash(tags)"));
+}
+
+#[test]
+fn test_core_type_json() {
+    let code = r#"
+@freezed
+abstract class Download with _$Download {
+  const factory Download({
+    required Duration timeout,
+    required Uri link,
+    Uri? mirror,
+    required BigInt size,
+    required Iterable<int> chunks,
+    required Iterable<DateTime> attempts,
+  }) = _Download;
+
+  factory Download.fromJson(Map<String, dynamic> json) => _$DownloadFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    assert!(json.contains("Duration(microseconds: (json['timeout'] as num).toInt())"));
+    assert!(json.contains("Uri.parse(json['link'] as String)"));
+    assert!(json.contains("(json['mirror'] == null ? null : Uri.parse(json['mirror'] as String))"));
+    assert!(json.contains("BigInt.parse(json['size'] as String)"));
+    assert!(
+        json.contains("(json['chunks'] as List<dynamic>).map(\n    (e) => (e as num).toInt() )")
+    );
+    assert!(json.contains("(e) => DateTime.parse(e as String) )"));
+
+    assert!(json.contains("'timeout': instance.timeout.inMicroseconds,"));
+    assert!(json.contains("'link': instance.link.toString(),"));
+    assert!(json.contains("'mirror': instance.mirror?.toString(),"));
+    assert!(json.contains("'size': instance.size.toString(),"));
+    assert!(json.contains("'chunks': instance.chunks.toList(),"));
+    assert!(
+        json.contains("'attempts': instance.attempts.map((e) => e.toIso8601String()).toList(),")
+    );
+}
+
+#[test]
+fn test_typed_converters_apply_to_elements() {
+    let code = r#"
+class EpochConverter implements JsonConverter<DateTime, int> {
+  const EpochConverter();
+
+  @override
+  DateTime fromJson(int json) => DateTime.fromMillisecondsSinceEpoch(json);
+
+  @override
+  int toJson(DateTime object) => object.millisecondsSinceEpoch;
+}
+
+const epochConverter = EpochConverter();
+
+@freezed
+abstract class Calendar with _$Calendar {
+  const factory Calendar({
+    @EpochConverter() required List<DateTime> dates,
+    @EpochConverter() required Map<String, DateTime> byName,
+    @epochConverter required DateTime created,
+  }) = _Calendar;
+
+  factory Calendar.fromJson(Map<String, dynamic> json) => _$CalendarFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    // List elements and map values, decoded and encoded
+    assert_eq!(
+        json.matches("const EpochConverter().fromJson(e as int)")
+            .count(),
+        2
+    );
+    assert_eq!(json.matches("const EpochConverter().toJson(e)").count(), 2);
+    assert!(!json.contains("toIso8601String"));
+
+    // A constant is used as it is
+    assert!(json.contains("epochConverter.fromJson(json['created']"));
+    assert!(json.contains("epochConverter.toJson(instance.created)"));
+    assert!(!json.contains("const epochConverter"));
+}
+
+#[test]
+fn test_class_converters() {
+    let code = r#"
+class EpochConverter implements JsonConverter<DateTime, int> {
+  const EpochConverter();
+
+  @override
+  DateTime fromJson(int json) => DateTime.fromMillisecondsSinceEpoch(json);
+
+  @override
+  int toJson(DateTime object) => object.millisecondsSinceEpoch;
+}
+
+class CentsConverter implements JsonConverter<double, int> {
+  const CentsConverter();
+
+  @override
+  double fromJson(int json) => json / 100;
+
+  @override
+  int toJson(double object) => (object * 100).round();
+}
+
+@JsonSerializable(converters: [EpochConverter()])
+@CentsConverter()
+@freezed
+abstract class Invoice with _$Invoice {
+  const factory Invoice({
+    required DateTime issued,
+    DateTime? paid,
+    required double total,
+    @ExternalConverter() required String note,
+  }) = _Invoice;
+
+  factory Invoice.fromJson(Map<String, dynamic> json) => _$InvoiceFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    // Applied to every field of their type
+    assert!(json.contains("const EpochConverter().fromJson(json['issued'] as int)"));
+    assert!(json.contains(
+        "(json['paid'] == null ? null : const EpochConverter().fromJson(json['paid'] as int))"
+    ));
+    assert!(json.contains("'issued': const EpochConverter().toJson(instance.issued),"));
+    assert!(json.contains(
+        "'paid': instance.paid == null ? null : const EpochConverter().toJson(instance.paid!),"
+    ));
+
+    // Only `converters:` configures the class, and converters outside the project aren't
+    // known to be any
+    assert!(!json.contains("CentsConverter"));
+    assert!(json.contains("(json['total'] as num).toDouble()"));
+    assert!(!json.contains("ExternalConverter"));
+    assert!(json.contains("((json['note']) as String)"));
+}
+
+#[test]
+fn test_nullable_collection_elements_json() {
+    let code = r#"
+@freezed
+abstract class Item with _$Item {
+  const factory Item(String name) = _Item;
+
+  factory Item.fromJson(Map<String, dynamic> json) => _$ItemFromJson(json);
+}
+
+@freezed
+abstract class Cart with _$Cart {
+  const factory Cart({
+    required List<Item?> items,
+    required Map<String, List<DateTime?>> history,
+  }) = _Cart;
+
+  factory Cart.fromJson(Map<String, dynamic> json) => _$CartFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    assert!(json.contains(
+        "(json['items'] as List<dynamic>).map(\n    (e) => (e == null ? null : Item.fromJson(e as Map<String, dynamic>)) ).toList()"
+    ));
+    assert!(json.contains(
+        "(json['history'] as Map<String, dynamic>).map(\n    (k, e) => MapEntry(k, (e as List<dynamic>).map(\n    (e) => (e == null ? null : DateTime.parse(e as String)) ).toList()))"
+    ));
+
+    assert!(json.contains("'items': instance.items,"));
+    assert!(json.contains(
+        "'history': instance.history.map((k, e) => MapEntry(k, e.map((e) => e?.toIso8601String()).toList())),"
+    ));
+}
+
+#[test]
+fn test_checked_from_json() {
+    let code = r#"
+@freezed
+@JsonSerializable(checked: true, disallowUnrecognizedKeys: true)
+abstract class Order with _$Order {
+  const factory Order(
+    @JsonKey(required: true, disallowNullValue: true) int id,
+    String? note,
+  ) = _Order;
+
+  factory Order.fromJson(Map<String, dynamic> json) => _$OrderFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    assert!(json.contains(
+        "Order _$OrderFromJson(Map<String, dynamic> json) => $checkedCreate(\n  'Order',\n  json,\n  ($checkedConvert) {"
+    ));
+    assert!(json.contains(
+        "  $checkKeys(json, allowedKeys: const ['id', 'note'], requiredKeys: const ['id'], disallowNullValues: const ['id']);"
+    ));
+    assert!(json.contains("$checkedConvert('id', (v) => (v as num).toInt()"));
+    assert!(json.contains("$checkedConvert('note', (v) => ((v) as String?)"));
+    assert!(json.contains("return val;"));
+
+    // Without `checked` the keys are still checked before decoding
+    let unchecked = code.replace("checked: true, ", "");
+    let (_, json) = generate_code(&unchecked);
+    assert!(
+        json.contains("Order _$OrderFromJson(Map<String, dynamic> json) {\n  $checkKeys(json, ")
+    );
+    assert!(!json.contains("$checkedCreate"));
+}
+
+#[test]
+fn test_json_key_default_and_read_value() {
+    use faster_freezed::json_serialization::{GeneratorOptions, JsonOptions};
+
+    let code = r#"
+Object? _readId(Map json, String key) => json[key] ?? json['uuid'];
+
+@freezed
+abstract class Job with _$Job {
+  const factory Job({
+    @JsonKey(readValue: _readId) required String id,
+    @JsonKey(defaultValue: 3) required int retries,
+    @JsonKey(defaultValue: 'low') required String priority,
+  }) = _Job;
+
+  factory Job.fromJson(Map<String, dynamic> json) => _$JobFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    assert!(json.contains("((_readId(json, 'id')) as String)"));
+    assert!(json.contains("(json['retries'] as num?)?.toInt()\n ?? 3"));
+    assert!(json.contains("((json['priority']) as String?)\n ?? 'low'"));
+
+    // In checked mode the value is read before being converted
+    let options = GeneratorOptions {
+        json: JsonOptions {
+            checked: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (_, json) = generate_code_with(code, &options);
+    assert!(json.contains("$checkedConvert('id', (v) => ((v) as String)\n\n, readValue: _readId)"));
+}
+
+#[test]
+fn test_any_map_json() {
+    let code = r#"
+@freezed
+abstract class Point with _$Point {
+  const factory Point(int x, int y) = _Point;
+
+  factory Point.fromJson(Map<String, dynamic> json) => _$PointFromJson(json);
+}
+
+@Freezed()
+@JsonSerializable(anyMap: true)
+abstract class Path with _$Path {
+  const factory Path({
+    required Point start,
+    Point? end,
+    required Map<String, int> weights,
+    required List<Point> points,
+  }) = _Path;
+
+  factory Path.fromJson(Map<String, dynamic> json) => _$PathFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    assert!(json.contains("Path _$PathFromJson(Map json) =>"));
+    assert!(json.contains("Point.fromJson(Map<String, dynamic>.from(json['start'] as Map))"));
+    assert!(json.contains(
+        "(json['end'] == null ? null : Point.fromJson(Map<String, dynamic>.from(json['end'] as Map)))"
+    ));
+    assert!(json.contains(
+        "(json['weights'] as Map).map(\n    (k, e) => MapEntry(k as String, (e as num).toInt()))"
+    ));
+    assert!(json.contains("(e) => Point.fromJson(Map<String, dynamic>.from(e as Map)) ).toList()"));
+
+    // Only the class asking for it
+    assert!(json.contains("Point _$PointFromJson(Map<String, dynamic> json) =>"));
+}
+
+#[test]
+fn test_create_factory_and_field_map() {
+    let code = r#"
+@JsonSerializable(
+  createFactory: false,
+  createFieldMap: true,
+  createPerFieldToJson: true,
+  fieldRename: FieldRename.snake,
+)
+@freezed
+abstract class Report with _$Report {
+  const factory Report(DateTime createdAt, @JsonKey(name: 'heading') String title) =
+      _Report;
+
+  factory Report.fromJson(Map<String, dynamic> json) => _$ReportFromJson(json);
+}
+"#;
+    let (_, json) = generate_code(code);
+
+    assert!(!json.contains("_$ReportFromJson"));
+    assert!(json.contains("Map<String, dynamic> _$ReportToJson(_Report instance) =>"));
+    assert!(json.contains(
+        "const _$ReportFieldMap = <String, String>{\n  'createdAt': 'created_at',\n  'title': 'heading',\n};"
+    ));
+    assert!(json.contains("abstract class _$ReportPerFieldToJson {"));
+    assert!(
+        json.contains(
+            "  static Object? createdAt(DateTime instance) => instance.toIso8601String();"
+        )
+    );
+}
+
+#[test]
+fn test_mixed_file_keeps_freezed_output_after_errors() {
+    use faster_freezed::GeneratedFile;
+    use faster_freezed::json_serialization::GeneratorOptions;
+
+    // `Broken` has no constructor, the plain class still gets its JSON functions
+    let code = r#"
+@freezed
+abstract class Broken with _$Broken {
+  final int a = 1;
+}
+
+@JsonSerializable()
+class Plain {
+  Plain(this.name);
+
+  factory Plain.fromJson(Map<String, dynamic> json) => _$PlainFromJson(json);
+
+  final String name;
+}
+"#;
+    let generated = generate_model(code, &GeneratorOptions::default());
+
+    assert!(
+        generated
+            .diagnostics
+            .iter()
+            .any(|e| e.code == "missing-constructor")
+    );
+    assert_eq!(generated.freezed, GeneratedFile::Keep);
+    let json = written(&generated.json);
+    assert!(json.contains("Plain _$PlainFromJson(Map<String, dynamic> json)"));
+    assert!(json.contains("Map<String, dynamic> _$PlainToJson(Plain instance)"));
+
+    // Without the freezed class there's nothing left to keep
+    let plain_only = &code[code.find("@JsonSerializable").unwrap()..];
+    let generated = generate_model(plain_only, &GeneratorOptions::default());
+    assert_eq!(generated.freezed, GeneratedFile::Remove);
+}
+
+#[test]
+fn test_build_yaml_options() {
+    use faster_freezed::build_config::BuildConfig;
+    use faster_freezed::json_serialization::{FieldRename, GeneratorOptions};
+
+    let config = BuildConfig::parse(
+        r#"
+targets:
+  $default:
+    builders:
+      freezed:
+        options:
+          format: false # unformatted anyway
+      json_serializable:
+        options:
+          explicit_to_json: true
+          field_rename: snake
+          include_if_null: false
+          ignore_unannotated: true
+          made_up: "yes"
+"#,
+        &GeneratorOptions::default(),
+    );
+
+    let json = &config.options.json;
+    assert!(json.explicit_to_json);
+    assert!(!json.include_if_null);
+    assert_eq!(json.field_rename, FieldRename::Snake);
+    assert_eq!(config.warnings.len(), 2);
+
+    assert_eq!(FieldRename::Snake.apply("createdAt"), "created_at");
+    assert_eq!(FieldRename::Kebab.apply("createdAt"), "created-at");
+    assert_eq!(FieldRename::ScreamingSnake.apply("createdAt"), "CREATED_AT");
+    assert_eq!(FieldRename::Pascal.apply("createdAt"), "CreatedAt");
+}
+
+#[test]
+fn test_build_yaml_flow_and_sequence_options() {
+    use faster_freezed::build_config::BuildConfig;
+    use faster_freezed::json_serialization::{FieldRename, GeneratorOptions};
+
+    let config = BuildConfig::parse(
+        r#"
+targets:
+  $default:
+    sources:
+      - lib/**
+      - test/**
+    builders:
+      json_serializable:
+        options: {checked: true, field_rename: 'kebab',
+          any_map: true}
+      freezed:
+        options:
+          union_key: type
+          union_value_case: screaming_snake
+  other:
+    builders:
+      json_serializable:
+        options: [checked]
+"#,
+        &GeneratorOptions::default(),
+    );
+
+    let json = &config.options.json;
+    assert!(json.checked);
+    assert!(json.any_map);
+    assert_eq!(json.field_rename, FieldRename::Kebab);
+    assert_eq!(config.options.freezed.union_key, "type");
+    assert_eq!(
+        config.options.freezed.union_value_case,
+        FieldRename::ScreamingSnake
+    );
+    assert_eq!(config.warnings.len(), 1, "{:?}", config.warnings);
+    assert!(config.warnings[0].contains("should be a mapping"));
+}
+
+#[test]
+fn test_build_yaml_anchors_and_malformed_files() {
+    use faster_freezed::build_config::BuildConfig;
+    use faster_freezed::json_serialization::{FieldRename, GeneratorOptions};
+
+    let config = BuildConfig::parse(
+        r#"
+global_options:
+  json_serializable:
+    options: &shared
+      field_rename: snake
+targets:
+  $default:
+    builders:
+      freezed:
+        options:
+      json_serializable|json_serializable:
+        options: *shared
+  tests:
+    builders:
+      json_serializable:
+        options: {checked: !!bool true}
+"#,
+        &GeneratorOptions::default(),
+    );
+    assert_eq!(config.options.json.field_rename, FieldRename::Snake);
+    assert!(config.options.json.checked);
+    assert!(config.warnings.is_empty(), "{:?}", config.warnings);
+
+    let config = BuildConfig::parse(
+        "targets:\n  $default: [unclosed\n",
+        &GeneratorOptions::default(),
+    );
+    assert_eq!(config.options.json, GeneratorOptions::default().json);
+    assert_eq!(config.warnings.len(), 1, "{:?}", config.warnings);
+    assert!(config.warnings[0].contains("couldn't parse it"));
+
+    let mut config = BuildConfig::parse("", &GeneratorOptions::default());
+    config.apply_lock("packages:\n  freezed: {version: \"2.5.7\"\n");
+    assert_eq!(config.warnings.len(), 1, "{:?}", config.warnings);
+    assert!(config.warnings[0].contains("pubspec.lock"));
+}
+
+#[test]
+fn test_union_json_uses_union_key() {
+    let code = r#"
+@Freezed(unionKey: 'kind', unionValueCase: FreezedUnionCase.snake)
+sealed class Shape with _$Shape {
+  const factory Shape.circle({required double radius}) = _Circle;
+  @FreezedUnionValue('box')
+  const factory Shape.bigSquare({required double side}) = _Square;
+
+  factory Shape.fromJson(Map<String, dynamic> json) => _$ShapeFromJson(json);
+}
+"#;
+    let (freezed, json) = generate_code(code);
+
+    assert!(freezed.contains("Shape _$ShapeFromJson(Map<String, dynamic> json) {"));
+    assert!(freezed.contains("  switch (json['kind']) {"));
+    assert!(freezed.contains("    case 'circle':\n      return _$_CircleFromJson(json);"));
+    assert!(freezed.contains("    case 'box':\n      return _$_SquareFromJson(json);"));
+    assert!(freezed.contains("_$_CircleToJson(this)"));
+
+    assert!(json.contains("_Circle _$_CircleFromJson(Map<String, dynamic> json)"));
+    assert!(json.contains("Map<String, dynamic> _$_SquareToJson(_Square instance)"));
+    assert!(json.contains("    'kind': 'circle',"));
+    assert!(json.contains("    'kind': 'box',"));
+    assert!(!json.contains("_$ShapeFromJson"));
+}
+
+#[test]
+fn test_traversal_of_nested_packages() {
+    use faster_freezed::project_files::{ProjectFiles, TraversalOptions};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    let root =
+        std::env::temp_dir().join(format!("faster_freezed_traversal_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for file in [
+        "pubspec.yaml",
+        "lib/model.dart",
+        "lib/generated/model.g.dart",
+        "lib/generated/model.freezed.dart",
+        "lib/build/kept.dart",
+        "build/output.dart",
+        ".dart_tool/cache.dart",
+        "example/pubspec.yaml",
+        "example/lib/main.dart",
+        "packages/core/pubspec.yaml",
+        "packages/core/lib/core.dart",
+        "packages/core/build/output.dart",
+    ] {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    let relative = |project_files: &ProjectFiles| {
+        let mut files: Vec<_> = project_files
+            .files
+            .iter()
+            .map(|(path, package)| {
+                let package = package.map(|e| project_files.package_roots[e].clone());
+                (path.strip_prefix(&root).unwrap().to_owned(), package)
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    let core = Some(faster_freezed::package_config::normalize_path(
+        &root.join("packages/core"),
+    ));
+
+    let project_files = ProjectFiles::collect(&root, &TraversalOptions::default());
+    let expected: Vec<(PathBuf, Option<PathBuf>)> = vec![
+        ("lib/build/kept.dart".into(), None),
+        ("lib/model.dart".into(), None),
+        ("packages/core/lib/core.dart".into(), core.clone()),
+    ];
+    assert_eq!(relative(&project_files), expected);
+
+    let options = TraversalOptions {
+        include_examples: true,
+        skipped_directories: vec![".dart_tool".to_owned()],
+    };
+    let project_files = ProjectFiles::collect(&root, &options);
+    let files: Vec<_> = relative(&project_files).into_iter().map(|e| e.0).collect();
+    assert!(files.contains(&Path::new("example/lib/main.dart").to_owned()));
+    assert!(files.contains(&Path::new("build/output.dart").to_owned()));
+    assert!(files.contains(&Path::new("packages/core/build/output.dart").to_owned()));
+    assert!(!files.contains(&Path::new(".dart_tool/cache.dart").to_owned()));
+    assert_eq!(project_files.package_roots.len(), 2);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_types_out_of_scope_arent_guessed_by_name() {
+    use faster_freezed::dart_types::DartType;
+    use faster_freezed::json_serialization::{GenerationContext, GeneratorOptions};
+    use faster_freezed::package_config::PackageConfig;
+    use faster_freezed::project_index::ProjectIndex;
+    use std::path::Path;
+
+    // Another package of the monorepo declares `Status`, this one doesn't import it
+    let mut index = ProjectIndex::new();
+    index.index_file(
+        Path::new("/mono/other/lib/status.dart"),
+        "enum Status { on, off }",
+    );
+    let path = Path::new("/mono/app/lib/model.dart");
+    index.index_file(path, "import 'package:unknown/status.dart';\n");
+
+    let config = PackageConfig::default();
+    let scope = index.scope_for(path, &config);
+    assert!(!scope.is_complete);
+    let options = GeneratorOptions::default();
+    let context = GenerationContext::new(&index, &scope, &config, &options);
+    assert!(context.resolve("Status").is_none());
+    assert!(!context.is_enum(&DartType {
+        name: "Status".to_owned(),
+        ..Default::default()
+    }));
+}
+
+#[test]
+fn test_pubspec_lock_versions() {
+    use faster_freezed::build_config::{BuildConfig, Version};
+    use faster_freezed::json_serialization::{FreezedVersion, GeneratorOptions};
+
+    let mut config = BuildConfig::parse("", &GeneratorOptions::default());
+    assert_eq!(config.options.freezed_version, FreezedVersion::V3);
+
+    config.apply_lock(
+        r#"# Generated by pub
+# See https://dart.dev/tools/pub/glossary#lockfile
+packages:
+  freezed:
+    dependency: "direct dev"
+    description:
+      name: freezed
+      url: "https://pub.dev"
+    source: hosted
+    version: "2.5.7"
+  json_serializable:
+    dependency: "direct dev"
+    source: hosted
+    version: "6.7.1"
+sdks:
+  dart: ">=3.4.0 <4.0.0"
+"#,
+    );
+
+    assert_eq!(config.options.freezed_version, FreezedVersion::V2);
+    assert_eq!(
+        config.options.json_serializable_version,
+        Some(Version::new(6, 7, 1))
+    );
+    assert_eq!(Version::parse("3.0.0-dev.1"), Some(Version::new(3, 0, 0)));
+    assert!(config.warnings.is_empty());
+}
+
+const SHAPE_UNION: &str = r#"
+@freezed
+sealed class Shape with _$Shape {
+  const factory Shape.circle(double radius) = Circle;
+  const factory Shape.square(double side) = Square;
+}
+"#;
+
+#[test]
+fn test_freezed_2_union_output() {
+    use faster_freezed::json_serialization::{FreezedVersion, GeneratorOptions};
+
+    let options = GeneratorOptions {
+        freezed_version: FreezedVersion::V2,
+        ..Default::default()
+    };
+    let (freezed, _) = generate_code_with(SHAPE_UNION, &options);
+
+    assert!(freezed.contains("  TResult when<TResult extends Object?>({"));
+    assert!(freezed.contains("    required TResult Function(double radius) circle,"));
+    assert!(freezed.contains("  TResult maybeWhen<TResult extends Object?>({"));
+    assert!(freezed.contains("    TResult Function(double side)? square,"));
+    assert!(freezed.contains("    required TResult orElse(),"));
+    assert!(freezed.contains("    if (self is Square) {"));
+    assert!(freezed.contains("abstract class $ShapeCopyWith<"));
+    assert!(!freezed.contains("abstract mixin class"));
+}
+
+#[test]
+fn test_freezed_3_union_output() {
+    use faster_freezed::json_serialization::GeneratorOptions;
+
+    let generated = generate_model(SHAPE_UNION, &GeneratorOptions::default());
+    let freezed = written(&generated.freezed);
+
+    assert!(!freezed.contains("when<"));
+    assert!(!freezed.contains("maybeWhen"));
+    assert!(freezed.contains("abstract mixin class $ShapeCopyWith<"));
+    assert!(
+        generated.diagnostics.is_empty(),
+        "{:?}",
+        generated.diagnostics
+    );
+}
+
+#[test]
+fn test_freezed_3_warns_about_concrete_classes() {
+    use faster_freezed::json_serialization::{FreezedVersion, GeneratorOptions};
+
+    let code = SHAPE_UNION.replace("sealed class", "class");
+    let generated = generate_model(&code, &GeneratorOptions::default());
+    let codes: Vec<_> = generated.diagnostics.iter().map(|e| e.code).collect();
+    assert_eq!(codes, ["concrete-freezed-class"]);
+
+    let options = GeneratorOptions {
+        freezed_version: FreezedVersion::V2,
+        ..Default::default()
+    };
+    assert!(generate_model(&code, &options).diagnostics.is_empty());
+}
+
+#[test]
+fn test_freezed_after_other_class_annotations() {
+    use faster_freezed::parser::parse_dart_code;
+
+    let code = r#"
+@immutable @freezed
+abstract class Account with _$Account {
+  const factory Account(String name) = _Account;
+}
+
+@JsonSerializable(fieldRename: FieldRename.snake)
+@Freezed(copyWith: false)
+@Deprecated('use Account')
+abstract class LegacyAccount with _$LegacyAccount {
+  const factory LegacyAccount(String name) = _LegacyAccount;
+}
+"#;
+    let parsed = parse_dart_code(code);
+    assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+    assert_eq!(parsed.classes.len(), 2);
+    assert!(parsed.classes.iter().all(|e| e.is_freezed()));
+
+    let names = |index: usize| -> Vec<_> {
+        parsed.classes[index]
+            .annotations
+            .iter()
+            .map(|e| e.name.clone())
+            .collect()
+    };
+    assert_eq!(names(0), ["immutable", "freezed"]);
+    assert_eq!(names(1), ["JsonSerializable", "Freezed", "Deprecated"]);
+
+    let legacy = &parsed.classes[1];
+    assert_eq!(
+        legacy
+            .get_annotation("Freezed")
+            .unwrap()
+            .get_named_argument("copyWith"),
+        Some("false")
+    );
+    assert_eq!(
+        legacy.get_annotation("Deprecated").unwrap().source,
+        "@Deprecated('use Account')"
+    );
+}
+
+#[test]
+fn test_spans_of_classes_parameters_and_diagnostics() {
+    use faster_freezed::dart_types::{Position, Span};
+    use faster_freezed::parser::parse_dart_code;
+
+    let span = |start_byte, end_byte, start: (usize, usize), end: (usize, usize)| Span {
+        start_byte,
+        end_byte,
+        start: Position {
+            line: start.0,
+            column: start.1,
+        },
+        end: Position {
+            line: end.0,
+            column: end.1,
+        },
+    };
+
+    let code = r#"
+@freezed
+abstract class Point with _$Point {
+  const factory Point({
+    @Default(0) int x,
+    required int y,
+  }) = _Point;
+}
+
+@freezed
+abstract class Broken with _$Broken {
+  const factory Broken({@Default() int x}) = _Broken;
+}
+"#;
+    let parsed = parse_dart_code(code);
+
+    // Annotations included, up to the closing brace
+    let point = &parsed.classes[0];
+    assert_eq!(point.span, span(1, 129, (2, 1), (8, 2)));
+    assert!(code[point.span.start_byte..].starts_with("@freezed"));
+
+    // Leading metadata and `required` included
+    let parameters = &point.redirecting_constructors[0]
+        .parameters
+        .named_parameters;
+    assert_eq!(parameters[0].span, span(74, 91, (5, 5), (5, 22)));
+    assert_eq!(parameters[1].span, span(97, 111, (6, 5), (6, 19)));
+    assert_eq!(
+        &code[parameters[1].span.start_byte..parameters[1].span.end_byte],
+        "required int y"
+    );
+
+    let [diagnostic] = parsed.diagnostics.as_slice() else {
+        panic!("expected one diagnostic, got {:?}", parsed.diagnostics);
+    };
+    assert_eq!(diagnostic.code, "invalid-default");
+    assert_eq!(diagnostic.span, span(202, 212, (12, 25), (12, 35)));
+    assert_eq!(diagnostic.span.to_string(), "12:25");
+}
+
+#[test]
+fn test_monorepo_packages_use_their_own_configuration() {
+    use std::fs;
+    use std::process::Command;
+
+    let root = std::env::temp_dir().join(format!("faster_freezed_monorepo_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let model = r#"import 'package:freezed_annotation/freezed_annotation.dart';
+
+part 'generated/model.freezed.dart';
+part 'generated/model.g.dart';
+
+@freezed
+abstract class Account with _$Account {
+  const factory Account({required String displayName}) = _Account;
+
+  factory Account.fromJson(Map<String, dynamic> json) => _$AccountFromJson(json);
+}
+"#;
+    for (file, content) in [
+        ("packages/legacy/pubspec.yaml", "name: legacy\n"),
+        (
+            "packages/legacy/build.yaml",
+            "targets:\n  $default:\n    builders:\n      json_serializable:\n        options:\n          field_rename: snake\n",
+        ),
+        (
+            "packages/legacy/pubspec.lock",
+            "packages:\n  freezed:\n    source: hosted\n    version: \"2.5.7\"\n",
+        ),
+        ("packages/legacy/lib/model.dart", model),
+        ("packages/current/pubspec.yaml", "name: current\n"),
+        ("packages/current/lib/model.dart", model),
+    ] {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_faster_freezed"))
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let generated = |package: &str, file: &str| {
+        fs::read_to_string(root.join(format!("packages/{package}/lib/generated/{file}"))).unwrap()
+    };
+    let legacy_json = generated("legacy", "model.g.dart");
+    let current_json = generated("current", "model.g.dart");
+    assert!(legacy_json.contains("json['display_name']"));
+    assert!(current_json.contains("json['displayName']"));
+
+    // freezed 2 from the lock file of one package only
+    let legacy_freezed = generated("legacy", "model.freezed.dart");
+    let current_freezed = generated("current", "model.freezed.dart");
+    assert!(legacy_freezed.contains("throw _privateConstructorUsedError"));
+    assert!(!current_freezed.contains("_privateConstructorUsedError"));
+
+    fs::remove_dir_all(&root).unwrap();
+}
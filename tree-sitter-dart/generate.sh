@@ -0,0 +1,12 @@
+#!/bin/sh
+# Generates parser.c from grammar.json with the tree-sitter CLI matching the
+# `tree-sitter` crate. The output is several megabytes, so it isn't tracked.
+set -e
+
+cd "$(dirname "$0")"
+out=$(mktemp -d)
+trap 'rm -rf "$out"' EXIT
+
+cp grammar.json "$out/"
+(cd "$out" && npx --yes tree-sitter-cli@0.24.7 generate grammar.json)
+cp "$out/src/parser.c" parser.c